@@ -22,7 +22,7 @@ use crate::{
     docker::DockerImage,
     fmt::{CompetitionMarkdown, IndividualMarkdown},
     group_env::{set_checko_git_account, GroupEnv},
-    read_groups, retry,
+    read_groups, retry, run_config_from_file,
     test_runner::{TestRunData, TestRunInput, TestRunResults},
     ui,
 };
@@ -148,6 +148,9 @@ pub enum BatchCli {
         /// The configs file specifying the groups which are part of the competition.
         #[clap(long, short)]
         groups: PathBuf,
+        /// A config describing additional programs to generate for each analysis.
+        #[clap(long)]
+        run_config: Option<PathBuf>,
         /// The name of the batch. Defaults to current time.
         #[clap(short, long, default_value_t = default_batch_name())]
         name: String,
@@ -207,15 +210,19 @@ impl BatchCli {
             BatchCli::Init {
                 programs,
                 groups,
+                run_config,
                 name,
             } => {
-                let programs = collect_programs(programs)?;
+                let mut programs = collect_programs(programs)?.canonicalize()?;
+                if let Some(run_config) = run_config {
+                    programs.extend_with_plan(&run_config_from_file(run_config)?.plan());
+                }
                 let groups = read_groups(groups)?;
 
                 let write_path = PathBuf::from(format!("{name}.json"));
                 let batch = Batch {
                     path: Some(write_path.clone()),
-                    programs: Arc::new(programs.canonicalize()?),
+                    programs: Arc::new(programs),
                     groups: groups
                         .groups
                         .into_iter()
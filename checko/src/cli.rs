@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use crate::{batch, collect_programs, test_runner::TestRunInput};
+use crate::{batch, collect_programs, run_config_from_file, test_runner::TestRunInput};
 
 use clap::Parser;
 use color_eyre::Result;
@@ -14,6 +14,9 @@ pub enum Cli {
         /// The configs file specifying the programs to run in the competition.
         #[clap(long, short)]
         programs: Vec<PathBuf>,
+        /// A config describing additional programs to generate for each analysis.
+        #[clap(long)]
+        run_config: Option<PathBuf>,
     },
     /// Subcommand for everything batch related.
     Batch {
@@ -28,11 +31,15 @@ pub enum Cli {
 impl Cli {
     pub async fn run(self) -> Result<()> {
         match self {
-            Cli::DumpPrograms { programs } => {
-                println!(
-                    "{}",
-                    toml::to_string_pretty(&collect_programs(programs)?.canonicalize()?)?
-                );
+            Cli::DumpPrograms {
+                programs,
+                run_config,
+            } => {
+                let mut programs = collect_programs(programs)?.canonicalize()?;
+                if let Some(run_config) = run_config {
+                    programs.extend_with_plan(&run_config_from_file(run_config)?.plan());
+                }
+                println!("{}", toml::to_string_pretty(&programs)?);
 
                 Ok(())
             }
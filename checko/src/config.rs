@@ -1,6 +1,8 @@
 //! Config definitions for program inputs and groups of group.
 
-use checkr::{env::Analysis, GeneratedProgram};
+use std::time::Duration;
+
+use checkr::{config::PlannedJob, env::Analysis, GeneratedProgram};
 use color_eyre::Result;
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
@@ -44,11 +46,27 @@ impl CanonicalProgramsConfig {
     pub(crate) fn get(&self, analysis: Analysis, input: ProgramId) -> &CanonicalProgramConfig {
         &self.envs[&analysis].programs[input.0]
     }
+    /// Generates the programs of the planned jobs and adds them to the config.
+    pub fn extend_with_plan(&mut self, jobs: &[PlannedJob]) {
+        for job in jobs {
+            let p = job.builder().build();
+            self.envs
+                .entry(job.analysis)
+                .or_default()
+                .programs
+                .push(CanonicalProgramConfig {
+                    src: p.cmds.to_string(),
+                    input: p.input.to_string(),
+                    shown: false,
+                    timeout: job.timeout,
+                });
+        }
+    }
 }
 
 impl CanonicalProgramConfig {
     pub fn generated_program(&self, analysis: Analysis) -> Result<GeneratedProgram> {
-        let builder = analysis.setup_generation();
+        let builder = analysis.setup_generation().timeout(self.timeout);
         Ok(builder.from_cmds_and_input(
             checkr::parse::parse_commands(&self.src).unwrap(),
             analysis.input_from_str(&self.input)?,
@@ -73,6 +91,9 @@ pub struct CanonicalProgramConfig {
     pub src: String,
     pub input: String,
     pub shown: bool,
+    /// The time the program may run for, if it differs from the default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<Duration>,
 }
 impl ProgramsConfig {
     pub fn extend(&mut self, other: Self) {
@@ -144,6 +165,7 @@ impl ProgramConfig {
             src: p.cmds.to_string(),
             input: p.input.to_string(),
             shown: self.shown,
+            timeout: None,
         })
     }
 }
@@ -49,6 +49,7 @@ impl std::fmt::Display for IndividualMarkdown<'_> {
                                 TestResultType::Mismatch { .. } => "Mismatch",
                                 TestResultType::TimeOut => "Time out",
                                 TestResultType::Error { .. } => "Error",
+                                TestResultType::Unsupported => "Unsupported",
                             }
                             .to_string(),
                             format!("{:?}", summary.time),
@@ -9,7 +9,7 @@ mod ui;
 
 use std::{fs, num::NonZeroUsize, path::Path, time::Duration};
 
-use checkr::config::RunOption;
+use checkr::config::{RunConfig, RunOption};
 use color_eyre::{eyre::Context, Result};
 use config::{GroupsConfig, ProgramsConfig};
 
@@ -22,6 +22,11 @@ pub fn run_options_from_file(path: impl AsRef<Path>) -> Result<RunOption> {
     Ok(parsed)
 }
 
+pub fn run_config_from_file(path: impl AsRef<Path>) -> Result<RunConfig> {
+    let p = path.as_ref();
+    RunConfig::load(p).wrap_err_with(|| format!("could not load run config from file {p:?}"))
+}
+
 pub fn retry<T, E>(
     tries: NonZeroUsize,
     delay: Duration,
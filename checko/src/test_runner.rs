@@ -14,7 +14,7 @@ use std::{
 };
 
 use checkr::{
-    driver::Driver,
+    driver::{Capabilities, Driver, PROBE_TIMEOUT},
     env::{self, Analysis, AnyEnvironment, Environment, ValidationResult},
 };
 use color_eyre::{
@@ -172,10 +172,18 @@ pub struct TestRunResultsSection {
 #[derive(Debug, Clone, Hash, Serialize, Deserialize)]
 pub enum TestResultType {
     CorrectTerminated,
-    CorrectNonTerminated { iterations: u64 },
-    Mismatch { reason: String },
+    CorrectNonTerminated {
+        iterations: u64,
+    },
+    Mismatch {
+        reason: String,
+    },
     TimeOut,
-    Error { description: String },
+    Error {
+        description: String,
+    },
+    /// The implementation reported that it does not support the analysis
+    Unsupported,
 }
 
 #[derive(Debug, Clone, Hash, Serialize, Deserialize)]
@@ -199,15 +207,18 @@ impl TestResultType {
 struct GroupResults<'a> {
     config: &'a CanonicalProgramsConfig,
     driver: &'a Driver,
+    capabilities: Capabilities,
 
     sections: Vec<TestRunResultsSection>,
 }
 
 impl GroupResults<'_> {
     async fn generate(config: &CanonicalProgramsConfig, driver: &Driver) -> Result<TestRunData> {
+        let capabilities = driver.capabilities(PROBE_TIMEOUT).await;
         let mut results = GroupResults {
             config,
             driver,
+            capabilities,
             sections: vec![],
         };
 
@@ -226,9 +237,14 @@ impl GroupResults<'_> {
         Ok(TestRunData::Sections(results.sections))
     }
     async fn push<E: Environment>(&mut self, env: &E) {
+        let programs = if self.capabilities.supports(E::ANALYSIS) {
+            generate_test_results(self.config, env, self.driver).await
+        } else {
+            unsupported_test_results::<E>(self.config)
+        };
         self.sections.push(TestRunResultsSection {
             analysis: E::ANALYSIS,
-            programs: generate_test_results(self.config, env, self.driver).await,
+            programs,
         });
     }
 }
@@ -240,7 +256,9 @@ async fn generate_test_results<E: Environment>(
 ) -> Vec<TestResult> {
     let mut results = vec![];
 
-    let Some(programs) = config.envs.get(&E::ANALYSIS) else { return vec![] };
+    let Some(programs) = config.envs.get(&E::ANALYSIS) else {
+        return vec![];
+    };
 
     for (pid, program) in programs.programs() {
         let generated = program.generated_program(env.analysis()).unwrap();
@@ -270,3 +288,20 @@ async fn generate_test_results<E: Environment>(
 
     results
 }
+
+fn unsupported_test_results<E: Environment>(config: &CanonicalProgramsConfig) -> Vec<TestResult> {
+    let Some(programs) = config.envs.get(&E::ANALYSIS) else {
+        return vec![];
+    };
+
+    programs
+        .programs()
+        .map(|(pid, program)| TestResult {
+            analysis: E::ANALYSIS,
+            id: pid,
+            result: TestResultType::Unsupported,
+            time: Duration::ZERO,
+            shown: program.shown,
+        })
+        .collect()
+}
@@ -80,6 +80,7 @@ pub enum Color {
     Orange,
     Red,
     Blue,
+    Gray,
 }
 
 impl Color {
@@ -90,6 +91,7 @@ impl Color {
             Color::Red => "#ef4444",
             // TODO
             Color::Blue => "blue",
+            Color::Gray => "#6b7280",
         }
     }
     fn tw(self) -> &'static str {
@@ -98,6 +100,7 @@ impl Color {
             Color::Orange => "orange-500",
             Color::Red => "red-500",
             Color::Blue => "blue-700",
+            Color::Gray => "gray-500",
         }
     }
 }
@@ -195,6 +198,9 @@ impl Row {
                                         },
                                         Color::Red,
                                     ),
+                                    TestResultType::Unsupported => {
+                                        ("Unsupported".to_string(), Color::Gray)
+                                    }
                                 }
                             });
 
@@ -0,0 +1,30 @@
+use checkr::{
+    bench_programs,
+    interpreter::{Interpreter, InterpreterMemory},
+    pg::{Determinism, ProgramGraph},
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+fn program_graphs(c: &mut Criterion) {
+    for (name, cmds) in bench_programs() {
+        c.bench_function(&format!("ProgramGraph::new on {name}"), |b| {
+            b.iter(|| ProgramGraph::new(Determinism::Deterministic, black_box(&cmds)))
+        });
+    }
+}
+
+fn interpreter(c: &mut Criterion) {
+    let (_, cmds) = bench_programs()
+        .into_iter()
+        .find(|(name, _)| *name == "counting loop")
+        .expect("the corpus contains the counting loop");
+    let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+    let memory = InterpreterMemory::zeroed_for(&cmds, 0);
+
+    c.bench_function("Interpreter::evaluate on counting loop", |b| {
+        b.iter(|| Interpreter::evaluate(10_000, black_box(memory.clone()), &pg))
+    });
+}
+
+criterion_group!(benches, program_graphs, interpreter);
+criterion_main!(benches);
@@ -24,6 +24,12 @@ pub trait Lattice: Sized + Clone {
     }
     fn lub(&self, other: &Self) -> Self;
     fn contains(&self, other: &Self) -> bool;
+    /// Over-approximates the join of `self` and `other`, such that repeatedly
+    /// widening an ascending chain stabilizes after finitely many steps. The
+    /// default is the join, which suffices for lattices of finite height.
+    fn widen(&self, other: &Self) -> Self {
+        self.lub(other)
+    }
 }
 
 pub trait Worklist {
@@ -116,6 +122,7 @@ pub fn mono_analysis<A: MonotoneFramework, W: Worklist>(
     };
     facts.insert(initial_node, initial);
 
+    let back_edges = pg.loop_back_edges();
     let mut calls = 0;
 
     while let Some(n) = worklist.extract(pg) {
@@ -134,7 +141,13 @@ pub fn mono_analysis<A: MonotoneFramework, W: Worklist>(
             let target = facts.get_mut(&to).unwrap();
 
             if !target.contains(&constraint) {
-                target.lub_extend(&constraint);
+                // NOTE: Every cycle passes through a back edge, so widening
+                // only there is enough to ensure termination
+                if back_edges.contains(&(e.from(), e.to())) {
+                    *target = target.widen(&constraint);
+                } else {
+                    target.lub_extend(&constraint);
+                }
                 worklist.insert(to);
             }
         }
@@ -214,6 +227,18 @@ where
             }
         })
     }
+
+    fn widen(&self, other: &Self) -> Self {
+        let mut result = self.clone();
+        for (k, b) in other {
+            if let Some(a) = result.get_mut(k) {
+                *a = a.widen(b);
+            } else {
+                result.insert(k.clone(), b.clone());
+            }
+        }
+        result
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -228,10 +253,10 @@ impl<'a> NodeOrder<'a> {
     pub fn parse(n: &'a str) -> Self {
         match n {
             _ if n.contains('▷') => NodeOrder::First,
-            "qS" => NodeOrder::First,
+            "qS" | "qStart" => NodeOrder::First,
 
             _ if n.contains('◀') => NodeOrder::Last,
-            "qF" => NodeOrder::Last,
+            "qF" | "qFinal" => NodeOrder::Last,
 
             _ if n.contains(|c: char| c.is_numeric()) => NodeOrder::Middle(
                 n.chars()
@@ -244,3 +269,65 @@ impl<'a> NodeOrder<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::{mono_analysis, Direction, FiFo, Lattice, MonotoneFramework};
+    use crate::{
+        parse::parse_commands,
+        pg::{Determinism, Edge, Node, ProgramGraph},
+    };
+
+    /// An upper bound on the number of edges taken, which has infinite
+    /// ascending chains in loops.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Steps(Option<u64>);
+
+    impl Lattice for Steps {
+        fn bottom() -> Self {
+            Steps(None)
+        }
+        fn lub(&self, other: &Self) -> Self {
+            Steps(self.0.max(other.0))
+        }
+        fn contains(&self, other: &Self) -> bool {
+            other.0 <= self.0
+        }
+        fn widen(&self, other: &Self) -> Self {
+            if self.contains(other) {
+                self.clone()
+            } else {
+                Steps(Some(u64::MAX))
+            }
+        }
+    }
+
+    struct CountSteps;
+
+    impl MonotoneFramework for CountSteps {
+        type Domain = Steps;
+
+        fn semantic(&self, _pg: &ProgramGraph, _e: &Edge, prev: &Self::Domain) -> Self::Domain {
+            Steps(prev.0.map(|n| n.saturating_add(1)))
+        }
+        fn direction() -> Direction {
+            Direction::Forward
+        }
+        fn initial(&self, _pg: &ProgramGraph) -> Self::Domain {
+            Steps(Some(0))
+        }
+    }
+
+    #[test]
+    fn widening_at_back_edges() {
+        let cmds = parse_commands("x := 1; do x > 0 -> x := x + 1 od").unwrap();
+        let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+
+        let results = mono_analysis::<_, FiFo>(CountSteps, &pg);
+
+        assert_eq!(results.facts[&Node::End], Steps(Some(u64::MAX)));
+        assert!(results.semantic_calls < 20);
+    }
+}
@@ -144,14 +144,27 @@ pub enum Command {
     Skip,
     If(Vec<Guard>),
     Loop(Vec<Guard>),
-    /// **Extension**
-    EnrichedLoop(Predicate, Vec<Guard>),
+    /// **Extension**: `do {inv} guards od`, or `do [inv: inv; var: variant]
+    /// guards od` with a variant for total correctness
+    EnrichedLoop(Predicate, Option<AExpr>, Vec<Guard>),
     /// **Extension**
     Annotated(Predicate, Commands, Predicate),
     /// **Extension**
     Break,
     /// **Extension**
     Continue,
+    /// **Extension**: `for i in from..to do cmds done`
+    For(Variable, AExpr, AExpr, Commands),
+    /// **Extension**: `(cmds [] cmds)`, which runs one of the alternatives
+    /// chosen non-deterministically. The parentheses keep the `[]` apart from
+    /// the one separating guards.
+    Choice(Vec<Commands>),
+    /// **Extension**: `precondition b`, which the initial memory of a test
+    /// should satisfy. It does nothing when executed.
+    Precondition(BExpr),
+    /// **Extension**: `postcondition b`, which the final memory of a test
+    /// must satisfy. It does nothing when executed.
+    Postcondition(BExpr),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -166,6 +179,17 @@ pub enum AExpr {
     Binary(Box<AExpr>, AOp, Box<AExpr>),
     Minus(Box<AExpr>),
     Function(Function),
+    /// **Extension**
+    Cast(Box<AExpr>, CastTarget),
+}
+
+/// The integer type an expression is converted to in [`AExpr::Cast`]. Casts
+/// truncate the value to the bit-width of the target, like `as` in Rust.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CastTarget {
+    I32,
+    U32,
+    U64,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -240,11 +264,37 @@ impl Command {
             Command::If(c) => guards_fv(c),
             Command::Loop(c) => guards_fv(c),
             // TODO: Maybe the pred should also be looked at?
-            Command::EnrichedLoop(_, c) => guards_fv(c),
+            Command::EnrichedLoop(_, _, c) => guards_fv(c),
             // TODO: Maybe the pred should also be looked at?
             Command::Annotated(_, c, _) => c.fv(),
             Command::Break => HashSet::default(),
             Command::Continue => HashSet::default(),
+            Command::For(_, _, _, _) => self.desugar().fv(),
+            Command::Choice(alternatives) => alternatives.iter().flat_map(|c| c.fv()).collect(),
+            Command::Precondition(b) | Command::Postcondition(b) => b.fv(),
+        }
+    }
+}
+impl Commands {
+    /// Whether the program contains a `(cmds [] cmds)` choice at any depth.
+    pub fn has_choice(&self) -> bool {
+        self.0.iter().any(|c| c.has_choice())
+    }
+}
+impl Command {
+    pub fn has_choice(&self) -> bool {
+        match self {
+            Command::Choice(_) => true,
+            Command::If(c) | Command::Loop(c) | Command::EnrichedLoop(_, _, c) => {
+                c.iter().any(|g| g.1.has_choice())
+            }
+            Command::Annotated(_, c, _) | Command::For(_, _, _, c) => c.has_choice(),
+            Command::Assignment(_, _)
+            | Command::Skip
+            | Command::Break
+            | Command::Continue
+            | Command::Precondition(_)
+            | Command::Postcondition(_) => false,
         }
     }
 }
@@ -279,6 +329,7 @@ impl AExpr {
             AExpr::Binary(l, _, r) => l.fv().union(&r.fv()).cloned().collect(),
             AExpr::Minus(x) => x.fv(),
             AExpr::Function(f) => f.fv(),
+            AExpr::Cast(x, _) => x.fv(),
         }
     }
 }
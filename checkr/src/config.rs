@@ -1,7 +1,12 @@
-use std::path::Path;
+use std::{path::Path, time::Duration};
 
-use crate::driver::{Driver, DriverError};
+use crate::{
+    driver::{Driver, DriverError, ResourceLimits},
+    env::{security::LatticeTemplate, Analysis},
+    ProgramGenerationBuilder,
+};
 
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,14 +17,258 @@ pub struct RunOption {
     pub watch: Vec<String>,
     #[serde(default)]
     pub ignore: Vec<String>,
+    /// The CPU time each run may use, in seconds
+    pub cpu_time_limit: Option<u64>,
+    /// The size of the address space of each run, in bytes
+    pub memory_limit: Option<u64>,
 }
 
 impl RunOption {
     pub async fn driver(&self, dir: impl AsRef<Path>) -> Result<Driver, DriverError> {
-        if let Some(compile) = &self.compile {
-            Driver::compile(dir, compile, &self.run).await
+        let driver = if let Some(compile) = &self.compile {
+            Driver::compile(dir, compile, &self.run).await?
         } else {
-            Ok(Driver::new(dir, &self.run))
+            Driver::new(dir, &self.run)
+        };
+        Ok(driver.with_limits(self.limits()))
+    }
+    pub fn limits(&self) -> ResourceLimits {
+        ResourceLimits {
+            cpu_time: self.cpu_time_limit.map(Duration::from_secs),
+            memory: self.memory_limit,
+        }
+    }
+}
+
+/// Describes how many programs to generate for each analysis, and how to
+/// generate and run them.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunConfig {
+    #[serde(default)]
+    pub envs: IndexMap<Analysis, AnalysisRunConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnalysisRunConfig {
+    /// The number of programs to generate
+    pub samples: usize,
+    /// The seed of the first program. Subsequent programs use the following
+    /// seeds. If not set, each program uses a random seed.
+    pub seed: Option<u64>,
+    pub fuel: Option<u32>,
+    #[serde(default)]
+    pub no_loop: bool,
+    #[serde(default)]
+    pub no_division: bool,
+    #[serde(default)]
+    pub generate_annotated: bool,
+    /// The number of classes in the lattices of generated security analysis
+    /// inputs, which are then random DAGs. If not set, the shape of each
+    /// lattice is chosen at random.
+    pub security_classes: Option<usize>,
+    /// The time each program is allowed to run, in seconds
+    pub timeout: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RunConfigError {
+    #[error("{analysis} must generate at least one sample")]
+    NoSamples { analysis: Analysis },
+    #[error("the timeout for {analysis} must be positive")]
+    ZeroTimeout { analysis: Analysis },
+    #[error("the fuel for {analysis} must be positive")]
+    ZeroFuel { analysis: Analysis },
+    #[error("the number of security classes for {analysis} must be positive")]
+    ZeroSecurityClasses { analysis: Analysis },
+    #[error("{analysis} does not use security classes")]
+    UnusedSecurityClasses { analysis: Analysis },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LoadRunConfigError {
+    #[error("could not read the run config")]
+    Read(#[source] std::io::Error),
+    #[error("could not parse the run config")]
+    Parse(#[source] toml::de::Error),
+    #[error("invalid run config")]
+    Invalid(#[source] RunConfigError),
+}
+
+/// A single program to generate and run, as planned by [`RunConfig::plan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedJob {
+    pub analysis: Analysis,
+    pub seed: Option<u64>,
+    pub fuel: Option<u32>,
+    pub no_loop: bool,
+    pub no_division: bool,
+    pub generate_annotated: bool,
+    pub lattice_template: Option<LatticeTemplate>,
+    pub timeout: Option<Duration>,
+}
+
+impl RunConfig {
+    /// Parses a config from TOML and [validates](RunConfig::validate) it.
+    /// Unknown keys are rejected.
+    pub fn from_toml_str(src: &str) -> Result<RunConfig, LoadRunConfigError> {
+        let config: RunConfig = toml::from_str(src).map_err(LoadRunConfigError::Parse)?;
+        config.validate().map_err(LoadRunConfigError::Invalid)?;
+        Ok(config)
+    }
+    /// Reads the TOML config at `path`, see [`RunConfig::from_toml_str`].
+    pub fn load(path: impl AsRef<Path>) -> Result<RunConfig, LoadRunConfigError> {
+        let src = std::fs::read_to_string(path).map_err(LoadRunConfigError::Read)?;
+        RunConfig::from_toml_str(&src)
+    }
+    /// Checks the values of the config which cannot be expressed in its type.
+    pub fn validate(&self) -> Result<(), RunConfigError> {
+        for (&analysis, config) in &self.envs {
+            if config.samples == 0 {
+                return Err(RunConfigError::NoSamples { analysis });
+            }
+            if config.timeout == Some(0) {
+                return Err(RunConfigError::ZeroTimeout { analysis });
+            }
+            if config.fuel == Some(0) {
+                return Err(RunConfigError::ZeroFuel { analysis });
+            }
+            match config.security_classes {
+                Some(_) if analysis != Analysis::Security => {
+                    return Err(RunConfigError::UnusedSecurityClasses { analysis })
+                }
+                Some(0) => return Err(RunConfigError::ZeroSecurityClasses { analysis }),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+    pub fn plan(&self) -> Vec<PlannedJob> {
+        self.envs
+            .iter()
+            .flat_map(|(&analysis, config)| {
+                (0..config.samples).map(move |idx| PlannedJob {
+                    analysis,
+                    seed: config.seed.map(|seed| seed.wrapping_add(idx as u64)),
+                    fuel: config.fuel,
+                    no_loop: config.no_loop,
+                    no_division: config.no_division,
+                    generate_annotated: config.generate_annotated,
+                    lattice_template: config.security_classes.map(LatticeTemplate::RandomDag),
+                    timeout: config.timeout.map(Duration::from_secs),
+                })
+            })
+            .collect()
+    }
+}
+
+impl PlannedJob {
+    pub fn builder(&self) -> ProgramGenerationBuilder {
+        self.analysis
+            .setup_generation()
+            .seed(self.seed)
+            .fuel(self.fuel)
+            .no_loop(self.no_loop)
+            .no_division(self.no_division)
+            .generate_annotated(self.generate_annotated)
+            .lattice_template(self.lattice_template)
+            .timeout(self.timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        driver::ResourceLimits,
+        env::{security::LatticeTemplate, Analysis},
+    };
+
+    use super::{LoadRunConfigError, RunConfig, RunConfigError, RunOption};
+
+    #[test]
+    fn run_option_limits() {
+        let run: RunOption = serde_json::from_str(
+            r#"{ "run": "./run.sh", "cpu_time_limit": 5, "memory_limit": 1000000 }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            run.limits(),
+            ResourceLimits {
+                cpu_time: Some(Duration::from_secs(5)),
+                memory: Some(1_000_000),
+            }
+        );
+    }
+
+    #[test]
+    fn run_config() {
+        let src = r#"
+            [envs.Sign]
+            samples = 2
+            seed = 10
+            no_loop = true
+            timeout = 10
+
+            [envs.Security]
+            samples = 1
+            security_classes = 4
+        "#;
+        let config = RunConfig::from_toml_str(src).unwrap();
+
+        let round_trip: RunConfig =
+            serde_json::from_str(&serde_json::to_string(&config).unwrap()).unwrap();
+        assert_eq!(config, round_trip);
+
+        let plan = config.plan();
+        assert_eq!(plan.len(), 3);
+        assert_eq!(plan[1].analysis, Analysis::Sign);
+        assert_eq!(plan[1].seed, Some(11));
+        assert!(plan[1].no_loop);
+        assert_eq!(plan[1].timeout, Some(Duration::from_secs(10)));
+        assert_eq!(plan[1].lattice_template, None);
+        assert_eq!(plan[2].analysis, Analysis::Security);
+        assert_eq!(plan[2].seed, None);
+        assert_eq!(
+            plan[2].lattice_template,
+            Some(LatticeTemplate::RandomDag(4))
+        );
+        assert_eq!(plan[1].builder().build().timeout, Duration::from_secs(10));
+        assert_eq!(plan[2].builder().build().timeout, crate::DEFAULT_TIMEOUT);
+
+        let err = RunConfig::from_toml_str("[envs.Sign]\nsamples = 1\nsample = 2\n").unwrap_err();
+        assert!(matches!(err, LoadRunConfigError::Parse(_)));
+        assert!(std::error::Error::source(&err)
+            .unwrap()
+            .to_string()
+            .contains("unknown field `sample`"));
+
+        for (src, expected) in [
+            (
+                "[envs.Sign]\nsamples = 0\n",
+                RunConfigError::NoSamples {
+                    analysis: Analysis::Sign,
+                },
+            ),
+            (
+                "[envs.Sign]\nsamples = 1\nsecurity_classes = 3\n",
+                RunConfigError::UnusedSecurityClasses {
+                    analysis: Analysis::Sign,
+                },
+            ),
+            (
+                "[envs.Security]\nsamples = 1\nsecurity_classes = 0\n",
+                RunConfigError::ZeroSecurityClasses {
+                    analysis: Analysis::Security,
+                },
+            ),
+        ] {
+            match RunConfig::from_toml_str(src) {
+                Err(LoadRunConfigError::Invalid(err)) => assert_eq!(err, expected),
+                res => panic!("expected {expected:?} for {src:?}, found {res:?}"),
+            }
         }
     }
 }
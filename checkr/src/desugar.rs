@@ -0,0 +1,110 @@
+//! Translation of syntactic sugar into the core commands of GCL.
+
+use crate::ast::{AExpr, AOp, BExpr, Command, Commands, Guard, RelOp, Target};
+
+impl Commands {
+    /// Replaces all sugared commands with their translation into core
+    /// commands. Annotations are kept in place.
+    pub fn desugar(&self) -> Commands {
+        Commands(self.0.iter().flat_map(|c| c.desugar().0).collect())
+    }
+}
+
+impl Command {
+    /// Translates the command into core commands. Most commands translate into
+    /// themselves, with their bodies desugared.
+    ///
+    /// `for i in from..to do cmds done` is translated into
+    /// `i := from; do i < to -> cmds; i := i + 1 od`.
+    pub fn desugar(&self) -> Commands {
+        let cmd = match self {
+            Command::Assignment(_, _)
+            | Command::Skip
+            | Command::Break
+            | Command::Continue
+            | Command::Precondition(_)
+            | Command::Postcondition(_) => self.clone(),
+            Command::If(guards) => Command::If(desugar_guards(guards)),
+            Command::Loop(guards) => Command::Loop(desugar_guards(guards)),
+            Command::EnrichedLoop(p, v, guards) => {
+                Command::EnrichedLoop(p.clone(), v.clone(), desugar_guards(guards))
+            }
+            Command::Annotated(p, c, q) => Command::Annotated(p.clone(), c.desugar(), q.clone()),
+            Command::Choice(alternatives) => {
+                Command::Choice(alternatives.iter().map(|c| c.desugar()).collect())
+            }
+            Command::For(i, from, to, body) => {
+                let i = || Target::Variable(i.clone());
+                let var = || AExpr::Reference(i());
+
+                let mut body = body.desugar();
+                body.0.push(Command::Assignment(
+                    i(),
+                    AExpr::binary(var(), AOp::Plus, AExpr::Number(1)),
+                ));
+
+                return Commands(vec![
+                    Command::Assignment(i(), from.clone()),
+                    Command::Loop(vec![Guard(BExpr::rel(var(), RelOp::Lt, to.clone()), body)]),
+                ]);
+            }
+        };
+        Commands(vec![cmd])
+    }
+}
+
+fn desugar_guards(guards: &[Guard]) -> Vec<Guard> {
+    guards
+        .iter()
+        .map(|Guard(b, c)| Guard(b.clone(), c.desugar()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        interpreter::{Interpreter, InterpreterMemory, TerminationState},
+        parse::parse_commands,
+        pg::{Determinism, ProgramGraph},
+    };
+
+    #[test]
+    fn desugar_for() {
+        let cmds = parse_commands("for i in 0..n do x := x + i done").unwrap();
+
+        assert_eq!(
+            cmds.desugar(),
+            parse_commands("i := 0; do i < n -> x := x + i; i := i + 1 od").unwrap()
+        );
+    }
+
+    #[test]
+    fn annotations_are_preserved() {
+        let cmds = parse_commands("for i in 0..2 do {x >= 0} x := x + 1 {x > 0} done").unwrap();
+
+        assert_eq!(
+            cmds.desugar(),
+            parse_commands("i := 0; do i < 2 -> {x >= 0} x := x + 1 {x > 0}; i := i + 1 od")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn nested_for() {
+        let cmds =
+            parse_commands("for i in 0..3 do for j in i..3 do x := x + 1 done; y := y + j done")
+                .unwrap();
+        let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+        let (trace, termination) =
+            Interpreter::evaluate(1000, InterpreterMemory::zeroed_for(&cmds, 0), &pg);
+        let last = trace.last().unwrap();
+
+        assert_eq!(termination, TerminationState::Terminated);
+        let value = |v: &str| last.memory.variables[&crate::ast::Variable(v.to_string())];
+        assert_eq!(value("x"), 6);
+        assert_eq!(value("y"), 9);
+        assert_eq!(value("i"), 3);
+    }
+}
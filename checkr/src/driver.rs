@@ -3,18 +3,120 @@ use std::{
     time::Duration,
 };
 
+use serde::{Deserialize, Serialize};
 use tokio::process::Command;
 use tracing::error;
 
 use crate::{
     ast::Commands,
     env::{Analysis, EnvError, Environment, Output},
+    progress::{self, ProgressCallback, ProgressEvent},
 };
 
 pub struct Driver {
     dir: PathBuf,
     run_cmd: String,
     compile_output: Option<std::process::Output>,
+    limits: ResourceLimits,
+}
+
+/// Limits on the resources of the processes run by a [`Driver`]. The limits
+/// are set with `setrlimit` on unix, and are ignored on other platforms.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// The CPU time a process may use, rounded up to whole seconds
+    pub cpu_time: Option<Duration>,
+    /// The size of the address space of a process in bytes
+    pub memory: Option<u64>,
+}
+
+impl ResourceLimits {
+    #[cfg(unix)]
+    fn apply(&self, cmd: &mut Command) {
+        let limits = *self;
+        if limits == ResourceLimits::default() {
+            return;
+        }
+        let set = |resource, soft: u64, hard: u64| {
+            let limit = libc::rlimit {
+                rlim_cur: soft as libc::rlim_t,
+                rlim_max: hard as libc::rlim_t,
+            };
+            // SAFETY: `limit` is a valid rlimit for the duration of the call
+            if unsafe { libc::setrlimit(resource, &limit) } == 0 {
+                Ok(())
+            } else {
+                Err(std::io::Error::last_os_error())
+            }
+        };
+        // SAFETY: The closure only calls `setrlimit`, which is async-signal-safe
+        unsafe {
+            cmd.pre_exec(move || {
+                if let Some(cpu_time) = limits.cpu_time {
+                    let secs = (cpu_time.as_secs() + u64::from(cpu_time.subsec_nanos() > 0)).max(1);
+                    // NOTE: The process is sent SIGXCPU at the soft limit,
+                    // and SIGKILL at the hard limit if it handles SIGXCPU
+                    set(libc::RLIMIT_CPU, secs, secs + 1)?;
+                }
+                if let Some(memory) = limits.memory {
+                    set(libc::RLIMIT_AS, memory, memory)?;
+                }
+                Ok(())
+            });
+        }
+    }
+    #[cfg(not(unix))]
+    fn apply(&self, _cmd: &mut Command) {}
+
+    /// Attributes the failure of a process to one of the limits, based on the
+    /// signal which terminated it. `SIGXCPU` and `SIGKILL` are sent at the
+    /// soft and hard CPU limits, while a process which fails to allocate
+    /// memory usually crashes with `SIGSEGV` or aborts with `SIGABRT`. Signals
+    /// are only attributed to limits which are configured. The messages
+    /// common runtimes print when running out of memory are a secondary hint,
+    /// for processes which exit with a status instead.
+    #[cfg(unix)]
+    fn exceeded_by(&self, output: &std::process::Output) -> Option<LimitExceeded> {
+        use std::os::unix::process::ExitStatusExt;
+
+        match output.status.signal() {
+            Some(libc::SIGXCPU | libc::SIGKILL) if self.cpu_time.is_some() => {
+                Some(LimitExceeded::Cpu)
+            }
+            Some(libc::SIGSEGV | libc::SIGABRT) if self.memory.is_some() => {
+                Some(LimitExceeded::Memory)
+            }
+            Some(_) => None,
+            None => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                if self.memory.is_some() && OUT_OF_MEMORY_MARKERS.iter().any(|m| stderr.contains(m))
+                {
+                    Some(LimitExceeded::Memory)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    fn exceeded_by(&self, _output: &std::process::Output) -> Option<LimitExceeded> {
+        None
+    }
+}
+
+#[cfg_attr(not(unix), allow(dead_code))]
+const OUT_OF_MEMORY_MARKERS: &[&str] = &[
+    "memory allocation of",
+    "memory exhausted",
+    "Cannot allocate memory",
+    "out of memory",
+    "MemoryError",
+];
+
+#[cfg_attr(not(unix), allow(dead_code))]
+enum LimitExceeded {
+    Cpu,
+    Memory,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -25,6 +127,52 @@ pub enum DriverError {
     CompileFailure(std::process::Output),
 }
 
+/// Implementations which do not support an analysis should exit with a
+/// non-zero status and print this marker to stderr, such that it can be told
+/// apart from a crash.
+pub const UNSUPPORTED_ANALYSIS_MARKER: &str = "unsupported analysis";
+
+/// The version of the protocol between the driver and implementations.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// The command implementations are run with to report their [`Capabilities`].
+pub const CAPABILITIES_COMMAND: &str = "capabilities";
+
+/// How long implementations are given to answer [`CAPABILITIES_COMMAND`] in
+/// [`Driver::capabilities`].
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What an implementation reports to support when probed with
+/// [`CAPABILITIES_COMMAND`]. Missing fields take their default values, where
+/// `analyses` being absent means that no analyses are ruled out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Capabilities {
+    pub version: u32,
+    /// The commands of the supported analyses, see [`Analysis::command`]
+    pub analyses: Option<Vec<String>>,
+    pub protocols: Vec<String>,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities {
+            version: PROTOCOL_VERSION,
+            analyses: None,
+            protocols: vec!["argv".to_string()],
+        }
+    }
+}
+
+impl Capabilities {
+    pub fn supports(&self, analysis: Analysis) -> bool {
+        match &self.analyses {
+            Some(analyses) => analyses.iter().any(|a| a == analysis.command()),
+            None => true,
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ExecError {
     #[error(transparent)]
@@ -37,6 +185,29 @@ pub enum ExecError {
     },
     #[error("command failed:\n  {}\n\n  {}", std::str::from_utf8(&_0.stdout).unwrap(), std::str::from_utf8(&_0.stderr).unwrap())]
     CommandFailed(std::process::Output, Duration),
+    #[error("the implementation does not support {analysis}")]
+    UnsupportedAnalysis {
+        analysis: Analysis,
+        output: std::process::Output,
+        time: Duration,
+    },
+    #[error("the command exceeded its CPU time limit")]
+    CpuLimitExceeded {
+        output: std::process::Output,
+        time: Duration,
+    },
+    #[error("the command exceeded its memory limit")]
+    MemoryLimitExceeded {
+        output: std::process::Output,
+        time: Duration,
+    },
+    #[error("the implementation reported invalid capabilities")]
+    InvalidCapabilities {
+        #[source]
+        source: serde_json::Error,
+        output: std::process::Output,
+        time: Duration,
+    },
     #[error("parse failed")]
     Parse {
         #[source]
@@ -52,12 +223,27 @@ impl Driver {
             dir: dir.as_ref().to_owned(),
             run_cmd: run_cmd.to_string(),
             compile_output: None,
+            limits: ResourceLimits::default(),
         }
     }
+    /// Limits the resources of the processes run by the driver.
+    pub fn with_limits(self, limits: ResourceLimits) -> Driver {
+        Driver { limits, ..self }
+    }
     pub async fn compile(
         dir: impl AsRef<Path>,
         compile: &str,
         run_cmd: &str,
+    ) -> Result<Driver, DriverError> {
+        Self::compile_with_progress(dir, compile, run_cmd, &()).await
+    }
+    /// Like [`Driver::compile`], but also reports the start and the outcome of
+    /// the compilation to `progress`.
+    pub async fn compile_with_progress(
+        dir: impl AsRef<Path>,
+        compile: &str,
+        run_cmd: &str,
+        progress: &impl ProgressCallback,
     ) -> Result<Driver, DriverError> {
         let mut args = compile.split(' ');
         let program = args.next().unwrap();
@@ -66,7 +252,17 @@ impl Driver {
         cmd.args(args);
         cmd.current_dir(&dir);
 
-        let compile_output = cmd.output().await.map_err(DriverError::RunCompile)?;
+        progress::emit(progress, ProgressEvent::CompileStarted);
+        let before = std::time::Instant::now();
+        let compile_output = cmd.output().await;
+        progress::emit(
+            progress,
+            ProgressEvent::CompileFinished {
+                time: before.elapsed(),
+                success: matches!(&compile_output, Ok(output) if output.status.success()),
+            },
+        );
+        let compile_output = compile_output.map_err(DriverError::RunCompile)?;
 
         if !compile_output.status.success() {
             return Err(DriverError::CompileFailure(compile_output));
@@ -76,6 +272,7 @@ impl Driver {
             dir: dir.as_ref().to_owned(),
             run_cmd: run_cmd.to_string(),
             compile_output: Some(compile_output),
+            limits: ResourceLimits::default(),
         })
     }
     fn new_command(&self) -> Command {
@@ -84,9 +281,48 @@ impl Driver {
         let mut cmd = Command::new(args.next().unwrap());
         cmd.args(args);
         cmd.current_dir(&self.dir);
+        // NOTE: Processes which outlive a timeout are killed rather than left
+        // running in the background
+        cmd.kill_on_drop(true);
+        self.limits.apply(&mut cmd);
 
         cmd
     }
+    /// Asks the implementation which analyses and protocols it supports, by
+    /// running it with [`CAPABILITIES_COMMAND`].
+    pub async fn probe(&self) -> Result<Capabilities, ExecError> {
+        let mut cmd = self.new_command();
+        cmd.arg(CAPABILITIES_COMMAND);
+
+        let before = std::time::Instant::now();
+        let cmd_output = cmd.output().await.map_err(|source| ExecError::RunExec {
+            cmd: self.run_cmd.clone(),
+            source,
+        })?;
+        let took = before.elapsed();
+
+        if !cmd_output.status.success() {
+            return Err(ExecError::CommandFailed(cmd_output, took));
+        }
+
+        serde_json::from_slice(&cmd_output.stdout).map_err(|source| {
+            ExecError::InvalidCapabilities {
+                source,
+                output: cmd_output,
+                time: took,
+            }
+        })
+    }
+    /// The capabilities of the implementation, or the defaults if it fails
+    /// the [probe](Driver::probe) or does not answer within `timeout`.
+    /// Implementations which predate the probe thereby have all analyses
+    /// attempted, rather than stalling the run if they wait for input.
+    pub async fn capabilities(&self, timeout: Duration) -> Capabilities {
+        match tokio::time::timeout(timeout, self.probe()).await {
+            Ok(Ok(capabilities)) => capabilities,
+            Ok(Err(_)) | Err(_) => Capabilities::default(),
+        }
+    }
     pub async fn exec_dyn_raw_cmds(
         &self,
         analysis: Analysis,
@@ -112,6 +348,29 @@ impl Driver {
             //     stderr = std::str::from_utf8(&cmd_output.stderr).unwrap(),
             //     "failed to run command",
             // );
+            match self.limits.exceeded_by(&cmd_output) {
+                Some(LimitExceeded::Cpu) => {
+                    return Err(ExecError::CpuLimitExceeded {
+                        output: cmd_output,
+                        time: took,
+                    })
+                }
+                Some(LimitExceeded::Memory) => {
+                    return Err(ExecError::MemoryLimitExceeded {
+                        output: cmd_output,
+                        time: took,
+                    })
+                }
+                None => {}
+            }
+            let stderr = String::from_utf8_lossy(&cmd_output.stderr);
+            if stderr.contains(UNSUPPORTED_ANALYSIS_MARKER) {
+                return Err(ExecError::UnsupportedAnalysis {
+                    analysis,
+                    output: cmd_output,
+                    time: took,
+                });
+            }
             return Err(ExecError::CommandFailed(cmd_output, took));
         }
 
@@ -179,3 +438,129 @@ pub struct ExecOutput<O> {
     pub parsed: O,
     pub took: Duration,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Capabilities, Driver, ExecError, ResourceLimits, CAPABILITIES_COMMAND,
+        UNSUPPORTED_ANALYSIS_MARKER,
+    };
+    use std::time::Duration;
+
+    use crate::{env::Analysis, tests::unique_temp_dir};
+
+    #[tokio::test]
+    async fn unsupported_analysis() {
+        let dir = unique_temp_dir("unsupported-analysis");
+        std::fs::write(
+            dir.join("run.sh"),
+            format!(
+                "if [ \"$1\" = sign ]; then echo '{UNSUPPORTED_ANALYSIS_MARKER}: $1' >&2; fi\nexit 1\n"
+            ),
+        )
+        .unwrap();
+        let driver = Driver::new(&dir, "sh run.sh");
+
+        let res = driver.exec_dyn_raw_cmds(Analysis::Sign, "skip", "{}").await;
+        assert!(matches!(
+            res,
+            Err(ExecError::UnsupportedAnalysis {
+                analysis: Analysis::Sign,
+                ..
+            })
+        ));
+
+        let res = driver
+            .exec_dyn_raw_cmds(Analysis::Security, "skip", "{}")
+            .await;
+        assert!(matches!(res, Err(ExecError::CommandFailed(..))));
+    }
+
+    #[tokio::test]
+    async fn probe_capabilities() {
+        let dir = unique_temp_dir("probe-capabilities");
+        std::fs::write(
+            dir.join("run.sh"),
+            format!(
+                "if [ \"$1\" = {CAPABILITIES_COMMAND} ]; then echo '{{ \"analyses\": [\"sign\"] }}'; exit 0; fi\nexit 1\n"
+            ),
+        )
+        .unwrap();
+        let driver = Driver::new(&dir, "sh run.sh");
+
+        let capabilities = driver.probe().await.unwrap();
+        assert_eq!(
+            capabilities,
+            Capabilities {
+                analyses: Some(vec!["sign".to_string()]),
+                ..Default::default()
+            }
+        );
+        assert!(capabilities.supports(Analysis::Sign));
+        assert!(!capabilities.supports(Analysis::Security));
+
+        std::fs::write(dir.join("run.sh"), "echo 'not json'\n").unwrap();
+        assert!(matches!(
+            driver.probe().await,
+            Err(ExecError::InvalidCapabilities { .. })
+        ));
+        assert_eq!(
+            driver.capabilities(Duration::from_secs(10)).await,
+            Capabilities::default()
+        );
+
+        std::fs::write(dir.join("run.sh"), "sleep 10\n").unwrap();
+        let before = std::time::Instant::now();
+        assert_eq!(
+            driver.capabilities(Duration::from_millis(100)).await,
+            Capabilities::default()
+        );
+        assert!(before.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn resource_limits() {
+        let dir = unique_temp_dir("resource-limits");
+        std::fs::write(dir.join("spin.sh"), "while :; do :; done\n").unwrap();
+        let driver = Driver::new(&dir, "sh spin.sh").with_limits(ResourceLimits {
+            cpu_time: Some(Duration::from_secs(1)),
+            ..Default::default()
+        });
+        let res = driver.exec_dyn_raw_cmds(Analysis::Sign, "skip", "{}").await;
+        assert!(matches!(res, Err(ExecError::CpuLimitExceeded { .. })));
+
+        let cpu = ResourceLimits {
+            cpu_time: Some(Duration::from_secs(10)),
+            ..Default::default()
+        };
+        let memory = ResourceLimits {
+            memory: Some(1_000_000_000),
+            ..Default::default()
+        };
+        let cases = [
+            ("kill -XCPU $$", cpu, "cpu"),
+            ("kill -KILL $$", cpu, "cpu"),
+            ("kill -SEGV $$", memory, "memory"),
+            ("kill -ABRT $$", memory, "memory"),
+            ("echo 'out of memory' >&2; exit 1", memory, "memory"),
+            // NOTE: Signals of limits which are not configured are crashes
+            ("kill -SEGV $$", cpu, "failed"),
+            ("kill -KILL $$", memory, "failed"),
+            ("kill -TERM $$", cpu, "failed"),
+            ("echo 'out of memory' >&2; exit 1", cpu, "failed"),
+        ];
+        for (script, limits, expected) in cases {
+            std::fs::write(dir.join("run.sh"), format!("{script}\n")).unwrap();
+            let driver = Driver::new(&dir, "sh run.sh").with_limits(limits);
+            let res = driver.exec_dyn_raw_cmds(Analysis::Sign, "skip", "{}").await;
+            let actual = match res {
+                Err(ExecError::CpuLimitExceeded { .. }) => "cpu",
+                Err(ExecError::MemoryLimitExceeded { .. }) => "memory",
+                Err(ExecError::CommandFailed(..)) => "failed",
+                other => panic!("unexpected result of {script:?}: {other:?}"),
+            };
+            assert_eq!(actual, expected, "{script:?} with {limits:?}");
+        }
+    }
+}
@@ -28,6 +28,10 @@ define_language! {
         LogicalLength(String),
         "fac" = Fac(Id),
         "fib" = Fib(Id),
+        // Casts
+        "i32" = CastI32(Id),
+        "u32" = CastU32(Id),
+        "u64" = CastU64(Id),
         // BExpr
         Bool(bool),
         // - Rel
@@ -114,6 +118,7 @@ impl IntoEgg for AExpr {
             AExpr::Binary(lhs, op, rhs) => format!("({op} {} {})", lhs.egg(), rhs.egg()),
             AExpr::Minus(e) => format!("(- 0 {})", e.egg()),
             AExpr::Function(fun) => fun.egg(),
+            AExpr::Cast(x, t) => format!("({t} {})", x.egg()),
         }
     }
 }
@@ -1,17 +1,12 @@
-use std::collections::HashMap;
-
-use graphviz_rust::dot_structures::{Attribute, Id};
-use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-use tracing::debug;
 
 use crate::{
     ast::Commands,
     generation::Generate,
-    pg::{Determinism, ProgramGraph},
+    pg::{Determinism, Node, NodeStyle, ProgramGraph},
 };
 
-use super::{Analysis, EnvError, Environment, Markdown, ToMarkdown};
+use super::{Analysis, EnvError, Environment, InputError, Markdown, ToMarkdown, ValidationResult};
 
 #[derive(Debug)]
 pub struct GraphEnv;
@@ -19,6 +14,10 @@ pub struct GraphEnv;
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GraphEnvInput {
     pub determinism: Determinism,
+    /// Whether to compare the graphs after contracting chains of `skip` edges,
+    /// see [`ProgramGraph::compress_skips`].
+    #[serde(default)]
+    pub normalized: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -33,13 +32,18 @@ impl Generate for GraphEnvInput {
         Self {
             // TODO
             determinism: Determinism::Deterministic,
+            normalized: false,
         }
     }
 }
 
 impl ToMarkdown for GraphEnvInput {
     fn to_markdown(&self) -> Markdown {
-        format!("**Determinism:** {:?}", self.determinism).into()
+        format!(
+            "**Determinism:** {}\n\n**Normalized:** {}",
+            self.determinism, self.normalized
+        )
+        .into()
     }
 }
 impl ToMarkdown for GraphEnvOutput {
@@ -55,13 +59,32 @@ impl Environment for GraphEnv {
 
     const ANALYSIS: Analysis = Analysis::Graph;
 
+    fn validate_input(&self, cmds: &Commands, input: &Self::Input) -> Result<(), InputError> {
+        InputError::check_determinism(cmds, input.determinism)
+    }
+
     fn run(
         &self,
         cmds: &crate::ast::Commands,
         input: &Self::Input,
     ) -> Result<Self::Output, EnvError> {
+        self.validate_input(cmds, input)
+            .map_err(|err| EnvError::invalid_input::<Self>(input, err))?;
+
         let pg = ProgramGraph::new(input.determinism, cmds);
-        Ok(GraphEnvOutput { dot: pg.dot() })
+        let pg = if input.normalized {
+            pg.compress_skips()
+        } else {
+            pg
+        };
+        // NOTE: The reference output is compared between runs, so it leaves
+        // out the time of generation
+        let dot = pg
+            .dot_document("G", &NodeStyle::Ascii)
+            .header(&cmds.to_string())
+            .timestamp(false)
+            .to_string();
+        Ok(GraphEnvOutput { dot })
     }
 
     fn validate(
@@ -71,178 +94,155 @@ impl Environment for GraphEnv {
         output: &Self::Output,
     ) -> Result<super::ValidationResult, EnvError> {
         let reference = self.run(cmds, input)?;
-
-        let (a_data, _a_node_mapping, a_graph) = dot_to_petgraph(&reference.dot);
-        let (b_data, _b_node_mapping, b_graph) = dot_to_petgraph(&output.dot);
-
-        eprintln!("{a_graph:?}");
-        eprintln!("{b_graph:?}");
-
-        let a_graph = &a_graph;
-        let b_graph = &b_graph;
-        // let mut binding_1 = |_a: &String, _b: &String| a == b;
-        let mut binding_1 = |_a: &String, _b: &String| true;
-        // let mut binding_2 = |_a: &String, _b: &String| a == b;
-        let mut binding_2 = |_a: &String, _b: &String| true;
-        let res = petgraph::algo::isomorphism::subgraph_isomorphisms_iter(
-            &a_graph,
-            &b_graph,
-            &mut binding_1,
-            &mut binding_2,
-        )
-        .unwrap();
-
-        let res = res.collect_vec();
-
-        for matching in res {
-            for (a_idx, b_idx) in matching.iter().copied().enumerate() {
-                let a = &a_data[&a_graph.raw_nodes()[a_idx].weight];
-                let b = &b_data[&b_graph.raw_nodes()[b_idx].weight];
-                eprintln!("{a:?} == {b:?}");
-
-                // a_graph.find_edge(a, b)
-
-                let a_edges = a_graph
-                    .edges_directed(
-                        petgraph::graph::NodeIndex::new(a_idx),
-                        petgraph::Direction::Outgoing,
-                    )
-                    .map(|e| e.weight())
-                    .collect_vec();
-                let b_edges = b_graph
-                    .edges_directed(
-                        petgraph::graph::NodeIndex::new(b_idx),
-                        petgraph::Direction::Outgoing,
-                    )
-                    .map(|e| e.weight())
-                    .collect_vec();
-                eprintln!("{a_edges:?} == {b_edges:?}");
+        let reference =
+            ProgramGraph::from_dot(&reference.dot).expect("the reference graph can be read back");
+        let submitted = match ProgramGraph::from_dot(&output.dot) {
+            Ok(pg) => pg,
+            Err(err) => {
+                return Ok(ValidationResult::Mismatch {
+                    reason: format!("could not read the graph: {err}"),
+                })
             }
+        };
+        let (reference, submitted) = if input.normalized {
+            (reference.compress_skips(), submitted.compress_skips())
+        } else {
+            (reference, submitted)
+        };
+
+        if isomorphic(&reference, &submitted) {
+            Ok(ValidationResult::CorrectTerminated)
+        } else {
+            Ok(ValidationResult::Mismatch {
+                reason: format!(
+                    "the graph does not match the reference:\n{}",
+                    reference.dot()
+                ),
+            })
         }
-
-        // println!(
-        //     "{}",
-        //     petgraph::dot::Dot::with_config(a_graph, &[petgraph::dot::Config::EdgeNoLabel])
-        // );
-        // println!(
-        //     "{}",
-        //     petgraph::dot::Dot::with_config(b_graph, &[petgraph::dot::Config::EdgeNoLabel])
-        // );
-
-        todo!("MADE IT TO THE END!");
     }
 }
 
-#[derive(Debug, Default)]
-struct Node {
-    attributes: Vec<Attribute>,
-    outgoing: Vec<String>,
-    ingoing: Vec<String>,
+/// Whether the graphs are equal up to the numbering of their nodes, where the
+/// start and the end must be mapped to each other and edges must have the same
+/// actions.
+fn isomorphic(a: &ProgramGraph, b: &ProgramGraph) -> bool {
+    let (a, _, _) = a.as_petgraph();
+    let (b, _, _) = b.as_petgraph();
+    petgraph::algo::is_isomorphic_matching(
+        &a,
+        &b,
+        |x, y| matches!((x, y), (Node::Node(_), Node::Node(_))) || x == y,
+        |x, y| x == y,
+    )
 }
-fn dot_to_petgraph(
-    dot: &str,
-) -> (
-    HashMap<String, Node>,
-    HashMap<String, petgraph::graph::NodeIndex>,
-    petgraph::Graph<String, String>,
-) {
-    let mut nodes = HashMap::<String, Node>::new();
-    let mut node_mapping = HashMap::<String, petgraph::graph::NodeIndex>::new();
-    let mut graph = petgraph::Graph::<String, String>::new();
-
-    let parsed = graphviz_rust::parse(dot).unwrap();
-
-    match parsed {
-        graphviz_rust::dot_structures::Graph::Graph { .. } => todo!(),
-        graphviz_rust::dot_structures::Graph::DiGraph { stmts, .. } => {
-            for stmt in stmts {
-                match stmt {
-                    graphviz_rust::dot_structures::Stmt::Node(n) => {
-                        node_mapping
-                            .entry(n.id.0.to_string())
-                            .or_insert_with_key(|k| graph.add_node(k.to_string()));
-
-                        nodes
-                            .entry(n.id.0.to_string())
-                            .or_default()
-                            .attributes
-                            .extend_from_slice(&n.attributes);
-                    }
-                    graphviz_rust::dot_structures::Stmt::Subgraph(_) => todo!(),
-                    graphviz_rust::dot_structures::Stmt::Attribute(_) => todo!(),
-                    graphviz_rust::dot_structures::Stmt::GAttribute(_) => todo!(),
-                    graphviz_rust::dot_structures::Stmt::Edge(e) => match e.ty {
-                        graphviz_rust::dot_structures::EdgeTy::Pair(a, b) => match (a, b) {
-                            (
-                                graphviz_rust::dot_structures::Vertex::N(a),
-                                graphviz_rust::dot_structures::Vertex::N(b),
-                            ) => {
-                                let a_id = *node_mapping
-                                    .entry(a.0.to_string())
-                                    .or_insert_with_key(|k| graph.add_node(k.to_string()));
-                                let b_id = *node_mapping
-                                    .entry(b.0.to_string())
-                                    .or_insert_with_key(|k| graph.add_node(k.to_string()));
-                                graph.add_edge(
-                                    a_id,
-                                    b_id,
-                                    e.attributes
-                                        .iter()
-                                        .find_map(|a| match (&a.0, &a.1) {
-                                            (Id::Plain(l), Id::Escaped(v)) if l == "label" => {
-                                                Some(v.to_string())
-                                            }
-                                            (Id::Html(_), Id::Html(_)) => todo!(),
-                                            (Id::Html(_), Id::Escaped(_)) => todo!(),
-                                            (Id::Html(_), Id::Plain(_)) => todo!(),
-                                            (Id::Html(_), Id::Anonymous(_)) => todo!(),
-                                            (Id::Escaped(_), Id::Html(_)) => todo!(),
-                                            (Id::Escaped(_), Id::Escaped(_)) => todo!(),
-                                            (Id::Escaped(_), Id::Plain(_)) => todo!(),
-                                            (Id::Escaped(_), Id::Anonymous(_)) => todo!(),
-                                            (Id::Plain(_), Id::Html(_)) => todo!(),
-                                            (Id::Plain(_), Id::Escaped(_)) => todo!(),
-                                            (Id::Plain(_), Id::Plain(_)) => todo!(),
-                                            (Id::Plain(_), Id::Anonymous(_)) => todo!(),
-                                            (Id::Anonymous(_), Id::Html(_)) => todo!(),
-                                            (Id::Anonymous(_), Id::Escaped(_)) => todo!(),
-                                            (Id::Anonymous(_), Id::Plain(_)) => todo!(),
-                                            (Id::Anonymous(_), Id::Anonymous(_)) => todo!(),
-                                        })
-                                        .unwrap(),
-                                );
-
-                                nodes
-                                    .entry(a.0.to_string())
-                                    .or_default()
-                                    .outgoing
-                                    .push(b.0.to_string());
-                                nodes
-                                    .entry(b.0.to_string())
-                                    .or_default()
-                                    .ingoing
-                                    .push(a.0.to_string());
-                            }
-                            (a, b) => todo!("{a:?} -> {b:?}"),
-                        },
-                        graphviz_rust::dot_structures::EdgeTy::Chain(_) => todo!(),
-                    },
-                }
-            }
-        }
+
+#[test]
+fn reproducible_header() {
+    let cmds = crate::parse::parse_commands("x := 1").unwrap();
+    let input = GraphEnvInput {
+        determinism: Determinism::Deterministic,
+        normalized: false,
+    };
+    let output = GraphEnv.run(&cmds, &input).unwrap();
+    assert_eq!(output, GraphEnv.run(&cmds, &input).unwrap());
+    assert_eq!(
+        output.dot,
+        format!(
+            "// Generated by checkr {}
+// Source hash: 1d0ae95214af2653
+digraph G {{
+  qStart[label=\"qStart\"]; qStart -> qFinal[label=\"x := 1\"]; qFinal[label=\"qFinal\"];
+}}",
+            env!("CARGO_PKG_VERSION")
+        )
+    );
+
+    // Submissions are free to leave out the header, or to include the time
+    let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+    let dated = pg
+        .dot_document("G", &NodeStyle::Ascii)
+        .header(&cmds.to_string())
+        .to_string();
+    for dot in [pg.dot(), dated] {
+        assert_eq!(
+            GraphEnv
+                .validate(&cmds, &input, &GraphEnvOutput { dot })
+                .unwrap(),
+            ValidationResult::CorrectTerminated
+        );
     }
+}
 
-    let start_node = nodes.iter().find_map(|(n, node)| {
-        (node.outgoing.len() == 1 && node.ingoing.is_empty()).then_some((n, node))
-    });
-    let end_node = nodes.iter().find_map(|(n, node)| {
-        (node.outgoing.is_empty() && node.ingoing.len() == 1).then_some((n, node))
-    });
+#[test]
+fn normalized_validation() {
+    let cmds = crate::parse::parse_commands("skip; skip; x := 1").unwrap();
+    let input = GraphEnvInput {
+        determinism: Determinism::Deterministic,
+        normalized: true,
+    };
+    let full = GraphEnvOutput {
+        dot: ProgramGraph::new(Determinism::Deterministic, &cmds).dot(),
+    };
+    let compressed = GraphEnv.run(&cmds, &input).unwrap();
+    assert_eq!(
+        ProgramGraph::from_dot(&compressed.dot)
+            .unwrap()
+            .edges()
+            .len(),
+        2
+    );
 
-    debug!(
-        start = format!("{start_node:?}"),
-        end = format!("{end_node:?}")
+    for output in [&full, &compressed] {
+        assert_eq!(
+            GraphEnv.validate(&cmds, &input, output).unwrap(),
+            ValidationResult::CorrectTerminated
+        );
+    }
+    let strict = GraphEnvInput {
+        normalized: false,
+        ..input
+    };
+    assert_eq!(
+        GraphEnv.validate(&cmds, &strict, &full).unwrap(),
+        ValidationResult::CorrectTerminated
     );
+    assert!(matches!(
+        GraphEnv.validate(&cmds, &strict, &compressed).unwrap(),
+        ValidationResult::Mismatch { .. }
+    ));
+
+    let wrong = GraphEnvOutput {
+        dot: full.dot.replace("x := 1", "x := 2"),
+    };
+    assert!(matches!(
+        GraphEnv.validate(&cmds, &input, &wrong).unwrap(),
+        ValidationResult::Mismatch { .. }
+    ));
+}
 
-    (nodes, node_mapping, graph)
+#[test]
+fn choices_are_non_deterministic() {
+    let cmds = crate::parse::parse_commands("(x := 1 [] x := 2)").unwrap();
+    let input = GraphEnvInput {
+        determinism: Determinism::Deterministic,
+        normalized: false,
+    };
+    assert_eq!(
+        GraphEnv.validate_input(&cmds, &input),
+        Err(InputError::InvalidDeterminism {
+            determinism: Determinism::Deterministic
+        })
+    );
+    // NOTE: The typed entry points check the input as well
+    assert!(matches!(
+        GraphEnv.run(&cmds, &input),
+        Err(EnvError::InvalidInputForProgram { .. })
+    ));
+
+    let input = GraphEnvInput {
+        determinism: Determinism::NonDeterministic,
+        ..input
+    };
+    assert!(GraphEnv.run(&cmds, &input).is_ok());
 }
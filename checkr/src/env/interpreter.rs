@@ -5,12 +5,16 @@ use serde::{Deserialize, Serialize};
 use crate::{
     ast::Commands,
     generation::Generate,
-    interpreter::{Configuration, Interpreter, InterpreterMemory, TerminationState},
+    interpreter::{
+        sample_memory, Configuration, Interpreter, InterpreterBounds, InterpreterLimits,
+        InterpreterMemory, TerminationState,
+    },
     pg::{Determinism, Node, ProgramGraph},
     sign::{Memory, MemoryRef},
+    uninit::uninitialized_reads,
 };
 
-use super::{Analysis, EnvError, Environment, Markdown, ToMarkdown, ValidationResult};
+use super::{Analysis, EnvError, Environment, InputError, Markdown, ToMarkdown, ValidationResult};
 
 #[derive(Debug)]
 pub struct InterpreterEnv;
@@ -20,16 +24,30 @@ pub struct InterpreterInput {
     pub determinism: Determinism,
     pub assignment: InterpreterMemory,
     pub trace_length: u64,
+    #[serde(default)]
+    pub limits: InterpreterLimits,
+    /// Whether to report reads of variables which might not have been
+    /// assigned yet, see [`uninitialized_reads`].
+    #[serde(default)]
+    pub check_uninitialized: bool,
 }
 
-impl Generate for InterpreterInput {
-    type Context = Commands;
-
-    fn gen<R: rand::Rng>(cx: &mut Self::Context, mut rng: &mut R) -> Self {
+impl InterpreterInput {
+    /// Generates an input where the initial values of the variables mentioned
+    /// in `bounds` lie within their ranges.
+    pub fn gen_with_bounds<R: rand::Rng>(
+        cx: &mut Commands,
+        rng: &mut R,
+        bounds: &InterpreterBounds,
+    ) -> Self {
+        let sampled = sample_memory(bounds, rng);
         let assignment = Memory::from_targets_with(
             cx.fv(),
-            &mut rng,
-            |rng, _| rng.gen_range(-10..=10),
+            &mut *rng,
+            |rng, var| match sampled.variables.get(var) {
+                Some(&value) => value,
+                None => rng.gen_range(bounds.range(var)),
+            },
             |rng, _| {
                 let len = rng.gen_range(5..=10);
                 (0..len).map(|_| rng.gen_range(-10..=10)).collect()
@@ -41,10 +59,20 @@ impl Generate for InterpreterInput {
                 .unwrap(),
             assignment,
             trace_length: rng.gen_range(10..=15),
+            limits: InterpreterLimits::default(),
+            check_uninitialized: false,
         }
     }
 }
 
+impl Generate for InterpreterInput {
+    type Context = Commands;
+
+    fn gen<R: rand::Rng>(cx: &mut Self::Context, rng: &mut R) -> Self {
+        Self::gen_with_bounds(cx, rng, &InterpreterBounds::default())
+    }
+}
+
 impl ToMarkdown for InterpreterInput {
     fn to_markdown(&self) -> Markdown {
         let mut table = comfy_table::Table::new();
@@ -52,13 +80,7 @@ impl ToMarkdown for InterpreterInput {
             .load_preset(comfy_table::presets::ASCII_MARKDOWN)
             .set_header(["Input"]);
 
-        table.add_row([
-            "Determinism:",
-            match self.determinism {
-                Determinism::Deterministic => "**✓**",
-                Determinism::NonDeterministic => "**✕**",
-            },
-        ]);
+        table.add_row(["Determinism:".to_string(), self.determinism.to_string()]);
 
         table.add_row([
             "Memory:".to_string(),
@@ -83,6 +105,14 @@ pub struct InterpreterOutput {
     execution_sequence: Vec<Configuration<String>>,
     #[serde(rename = "final")]
     final_state: TerminationState,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    uninitialized_reads: Vec<String>,
+}
+
+impl InterpreterOutput {
+    pub fn execution_sequence(&self) -> &[Configuration<String>] {
+        &self.execution_sequence
+    }
 }
 
 impl ToMarkdown for InterpreterOutput {
@@ -137,10 +167,22 @@ impl ToMarkdown for InterpreterOutput {
             }
             TerminationState::Stuck => "**Stuck**".to_string(),
             TerminationState::Terminated => "**Terminated successfully**".to_string(),
+            TerminationState::MemoryLimitExceeded => "**Memory limit exceeded**".to_string(),
         };
         table.add_row([final_message]);
 
-        format!("{table}").into()
+        if self.uninitialized_reads.is_empty() {
+            format!("{table}").into()
+        } else {
+            format!(
+                "{table}\n\n**Uninitialized reads**\n\n{}",
+                self.uninitialized_reads
+                    .iter()
+                    .map(|r| format!("- {r}"))
+                    .format("\n")
+            )
+            .into()
+        }
     }
 }
 
@@ -151,18 +193,40 @@ impl Environment for InterpreterEnv {
 
     const ANALYSIS: Analysis = Analysis::Interpreter;
 
+    fn validate_input(&self, cmds: &Commands, input: &Self::Input) -> Result<(), InputError> {
+        InputError::check_determinism(cmds, input.determinism)?;
+        InputError::check_memory(cmds.fv(), &input.assignment)
+    }
+
     fn run(&self, cmds: &Commands, input: &Self::Input) -> Result<Self::Output, EnvError> {
+        self.validate_input(cmds, input)
+            .map_err(|err| EnvError::invalid_input::<Self>(input, err))?;
+
         let pg = ProgramGraph::new(input.determinism, cmds);
-        let (execution_sequence, final_state) =
-            Interpreter::evaluate(input.trace_length, input.assignment.clone(), &pg);
+        let (execution_sequence, final_state) = Interpreter::evaluate_with_limits(
+            input.trace_length,
+            input.assignment.clone(),
+            &pg,
+            &input.limits,
+        );
         let execution_sequence = execution_sequence
             .into_iter()
             .map(|t| t.map_node(|n| n.to_string()))
             .collect();
 
+        let uninitialized_reads = if input.check_uninitialized {
+            uninitialized_reads(cmds)
+                .iter()
+                .map(|r| r.to_string())
+                .collect()
+        } else {
+            vec![]
+        };
+
         Ok(InterpreterOutput {
             execution_sequence,
             final_state,
+            uninitialized_reads,
         })
     }
 
@@ -175,6 +239,9 @@ impl Environment for InterpreterEnv {
     where
         Self::Output: PartialEq,
     {
+        self.validate_input(cmds, input)
+            .map_err(|err| EnvError::invalid_input::<Self>(input, err))?;
+
         if let TerminationState::Running = output.final_state {
             if output.execution_sequence.len() < input.trace_length as usize {
                 return Ok(ValidationResult::Mismatch {
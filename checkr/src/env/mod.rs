@@ -1,10 +1,17 @@
-use std::{ops::Deref, str::FromStr};
+use std::{collections::HashMap, ops::Deref, str::FromStr};
 
 use itertools::Either;
 use rand::rngs::SmallRng;
 use serde::{Deserialize, Serialize};
 
-use crate::{ast::Commands, generation::Generate, sign::Memory, ProgramGenerationBuilder};
+use crate::{
+    ast::{Array, Commands, Target, Variable},
+    generation::Generate,
+    pg::Determinism,
+    security::SecurityClass,
+    sign::Memory,
+    ProgramGenerationBuilder,
+};
 pub use graph::GraphEnv;
 pub use interpreter::InterpreterEnv;
 pub use parse::ParseEnv;
@@ -30,20 +37,29 @@ macro_rules! define_analysis {
         }
 
         impl Analysis {
+            pub const ALL: &'static [Analysis] = &[$( Analysis::$name, )*];
+
             pub fn command(&self) -> &'static str {
                 match self {
                     $( Analysis::$name => $cmd, )*
                 }
             }
+            /// The commands of all analyses, in the same order as [`Analysis::ALL`].
+            pub fn commands() -> &'static [&'static str] {
+                &[$( $cmd, )*]
+            }
         }
 
         impl FromStr for Analysis {
-            type Err = ();
+            type Err = UnknownAnalysis;
 
             fn from_str(s: &str) -> Result<Self, Self::Err> {
                 match s {
                     $( $cmd => Ok(Analysis::$name), )*
-                    _ => Err(()),
+                    _ => Err(UnknownAnalysis {
+                        command: s.to_string(),
+                        expected: Analysis::commands(),
+                    }),
                 }
             }
         }
@@ -70,6 +86,11 @@ macro_rules! define_analysis {
                     $( AnalysisInput::$name(_) => Analysis::$name, )*
                 }
             }
+            pub fn to_input(&self) -> Input {
+                match self {
+                    $( AnalysisInput::$name(input) => Input::from_concrete::<$env>(input), )*
+                }
+            }
         }
 
         #[typeshare::typeshare]
@@ -84,6 +105,19 @@ macro_rules! define_analysis {
                     $( AnalysisOutput::$name(_) => Analysis::$name, )*
                 }
             }
+            pub fn to_output(&self) -> Output {
+                match self {
+                    $( AnalysisOutput::$name(output) => Output::from_concrete::<$env>(output), )*
+                }
+            }
+        }
+
+        impl Output {
+            pub fn into_analysis_output(self) -> Result<AnalysisOutput, EnvError> {
+                match self.analysis {
+                    $( Analysis::$name => Ok(AnalysisOutput::$name(self.parsed::<$env>()?)), )*
+                }
+            }
         }
     };
 }
@@ -159,6 +193,14 @@ pub trait Environment {
         ProgramGenerationBuilder::new(Self::ANALYSIS)
     }
 
+    /// Checks that the input is well-formed for the given program. This is
+    /// called before [`Environment::run`] when dispatching through
+    /// [`AnyEnvironment`], and implementations which override it should call
+    /// it from their own `run` and `validate` as well.
+    fn validate_input(&self, _cmds: &Commands, _input: &Self::Input) -> Result<(), InputError> {
+        Ok(())
+    }
+
     fn run(&self, cmds: &Commands, input: &Self::Input) -> Result<Self::Output, EnvError>;
 
     fn validate(
@@ -167,6 +209,61 @@ pub trait Environment {
         input: &Self::Input,
         output: &Self::Output,
     ) -> Result<ValidationResult, EnvError>;
+
+    /// Whether two outputs carry the same result. By default they are compared
+    /// by their serialization, which is insensitive to the order of facts
+    /// stored in sets, but analyses with facts stored in lists should compare
+    /// those regardless of their order.
+    fn equivalent_outputs(&self, a: &Self::Output, b: &Self::Output) -> bool {
+        serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum InputError {
+    #[error("variable `{var}` was not in the given input")]
+    UnknownVariable { var: Variable },
+    #[error("array `{arr}` was not in the given input")]
+    UnknownArray { arr: Array },
+    #[error("a program with non-deterministic choices cannot be {determinism}")]
+    InvalidDeterminism { determinism: Determinism },
+    #[error("the given memory does not match the size expected by the program")]
+    InconsistentMemorySize,
+    #[error("security class `{class}` is not in the given lattice")]
+    UnknownSecurityClass { class: SecurityClass },
+}
+
+impl InputError {
+    /// Checks that every target in `targets` has an entry in `memory`.
+    pub fn check_memory<T, A>(
+        targets: impl IntoIterator<Item = Target>,
+        memory: &Memory<T, A>,
+    ) -> Result<(), InputError> {
+        for t in targets {
+            match t {
+                Target::Variable(var) => {
+                    if memory.get_var(&var).is_none() {
+                        return Err(InputError::UnknownVariable { var });
+                    }
+                }
+                Target::Array(arr, ()) => {
+                    if memory.get_arr(&arr).is_none() {
+                        return Err(InputError::UnknownArray { arr });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that `cmds` can be run with the given `determinism`. A choice
+    /// between alternatives is non-deterministic, regardless of the guards.
+    pub fn check_determinism(cmds: &Commands, determinism: Determinism) -> Result<(), InputError> {
+        if determinism == Determinism::Deterministic && cmds.has_choice() {
+            return Err(InputError::InvalidDeterminism { determinism });
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -252,6 +349,8 @@ pub trait AnyEnvironment {
         output: Output,
     ) -> Result<ValidationResult, EnvError>;
 
+    fn equivalent_outputs(&self, a: Output, b: Output) -> Result<bool, EnvError>;
+
     fn input_markdown(&self, input: Input) -> Result<Markdown, EnvError>;
     fn output_markdown(&self, output: Output) -> Result<Markdown, EnvError>;
 
@@ -271,9 +370,12 @@ impl<E: Environment + ?Sized> AnyEnvironment for E {
     }
 
     fn run(&self, cmds: &Commands, input: Input) -> Result<Output, EnvError> {
+        let input = input.parsed::<E>()?;
+        self.validate_input(cmds, &input)
+            .map_err(|err| EnvError::invalid_input::<E>(&input, err))?;
         Ok(Output {
             analysis: self.analysis(),
-            json: serde_json::to_value(&self.run(cmds, &input.parsed::<E>()?)?)
+            json: serde_json::to_value(&self.run(cmds, &input)?)
                 .expect("all output should be serializable"),
         })
     }
@@ -292,7 +394,14 @@ impl<E: Environment + ?Sized> AnyEnvironment for E {
         input: Input,
         output: Output,
     ) -> Result<ValidationResult, EnvError> {
-        self.validate(cmds, &input.parsed::<E>()?, &output.parsed::<E>()?)
+        let input = input.parsed::<E>()?;
+        self.validate_input(cmds, &input)
+            .map_err(|err| EnvError::invalid_input::<E>(&input, err))?;
+        self.validate(cmds, &input, &output.parsed::<E>()?)
+    }
+
+    fn equivalent_outputs(&self, a: Output, b: Output) -> Result<bool, EnvError> {
+        Ok(self.equivalent_outputs(&a.parsed::<E>()?, &b.parsed::<E>()?))
     }
 
     fn input_markdown(&self, input: Input) -> Result<Markdown, EnvError> {
@@ -354,6 +463,13 @@ impl<E: Environment + ?Sized> AnyEnvironment for E {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown analysis `{command}`, expected one of: {}", expected.join(", "))]
+pub struct UnknownAnalysis {
+    pub command: String,
+    pub expected: &'static [&'static str],
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum EnvError {
     #[error("failed to parse json input: {source}")]
@@ -370,6 +486,15 @@ pub enum EnvError {
     InvalidInputForProgram { input: Input, message: String },
 }
 
+impl EnvError {
+    pub fn invalid_input<E: Environment + ?Sized>(input: &E::Input, err: InputError) -> Self {
+        EnvError::InvalidInputForProgram {
+            input: Input::from_concrete::<E>(input),
+            message: err.to_string(),
+        }
+    }
+}
+
 impl Analysis {
     pub fn as_env(&self) -> &dyn AnyEnvironment {
         self.deref()
@@ -380,6 +505,23 @@ impl Analysis {
     }
 }
 
+/// Runs every analysis in `inputs` on the same program, collecting the results
+/// of each, including the ones that failed.
+pub fn run_all_analyses(
+    cmds: &Commands,
+    inputs: &HashMap<Analysis, AnalysisInput>,
+) -> HashMap<Analysis, Result<AnalysisOutput, EnvError>> {
+    inputs
+        .iter()
+        .map(|(&analysis, input)| {
+            let output = analysis
+                .run(cmds, input.to_input())
+                .and_then(Output::into_analysis_output);
+            (analysis, output)
+        })
+        .collect()
+}
+
 impl<T, A> Generate for Memory<T, A>
 where
     T: Generate<Context = Commands>,
@@ -396,3 +538,45 @@ where
         )
     }
 }
+
+#[test]
+fn unknown_analysis() {
+    assert_eq!("sign".parse(), Ok(Analysis::Sign));
+    assert_eq!(Analysis::ALL.len(), Analysis::commands().len());
+    for (analysis, cmd) in Analysis::ALL.iter().zip(Analysis::commands()) {
+        assert_eq!(analysis.command(), *cmd);
+    }
+
+    let err = "model-check".parse::<Analysis>().unwrap_err();
+    assert_eq!(err.command, "model-check");
+    assert_eq!(
+        err.to_string(),
+        "unknown analysis `model-check`, expected one of: graph, parse, interpreter, \
+         program-verification, sign, security"
+    );
+}
+
+#[test]
+fn run_all() {
+    use rand::SeedableRng;
+
+    let cmds = crate::parse::parse_commands("x := 1; y := x").unwrap();
+    let mut rng = SmallRng::seed_from_u64(0);
+    let inputs: HashMap<_, _> = [Analysis::Sign, Analysis::Security]
+        .into_iter()
+        .map(|analysis| {
+            let input = analysis.gen_input(&cmds, &mut rng);
+            let input = match analysis {
+                Analysis::Sign => AnalysisInput::Sign(input.parsed::<SignEnv>().unwrap()),
+                _ => AnalysisInput::Security(input.parsed::<SecurityEnv>().unwrap()),
+            };
+            (analysis, input)
+        })
+        .collect();
+
+    let outputs = run_all_analyses(&cmds, &inputs);
+    assert_eq!(outputs.len(), 2);
+    for (analysis, output) in outputs {
+        assert_eq!(output.unwrap().analysis(), analysis);
+    }
+}
@@ -1,16 +1,19 @@
+use std::collections::HashSet;
+
 use itertools::Itertools;
 
 use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    ast::{Commands, Target},
+    ast::Commands,
+    dot::DotDocument,
     generation::Generate,
     security::{Flow, SecurityAnalysisOutput, SecurityClass, SecurityLattice},
     sign::Memory,
 };
 
-use super::{Analysis, EnvError, Environment, Markdown, ToMarkdown, ValidationResult};
+use super::{Analysis, EnvError, Environment, InputError, Markdown, ToMarkdown, ValidationResult};
 
 #[derive(Debug)]
 pub struct SecurityEnv;
@@ -24,44 +27,126 @@ pub struct SecurityAnalysisInput {
     pub lattice: SecurityLatticeInput,
 }
 
-impl Generate for SecurityAnalysisInput {
-    type Context = Commands;
+/// The shapes of lattices used when generating inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LatticeTemplate {
+    /// A single chain of the given number of classes
+    Chain(usize),
+    /// A bottom and a top class, connected through two incomparable classes
+    Diamond,
+    /// The chains `Public < Internal < Private` and `Trusted < Dubious`
+    TwoChains,
+    /// A random connected DAG of the given number of classes
+    RandomDag(usize),
+}
 
-    fn gen<R: rand::Rng>(cx: &mut Self::Context, rng: &mut R) -> Self {
-        let private = SecurityClass("Private".to_string());
-        let internal = SecurityClass("Internal".to_string());
-        let public = SecurityClass("Public".to_string());
-        let dubious = SecurityClass("Dubious".to_string());
-        let trusted = SecurityClass("Trusted".to_string());
-        let classes = [&private, &internal, &public, &dubious, &trusted].map(Clone::clone);
+impl LatticeTemplate {
+    pub fn random<R: rand::Rng>(rng: &mut R) -> LatticeTemplate {
+        match rng.gen_range(0..4) {
+            0 => LatticeTemplate::Chain(rng.gen_range(2..=5)),
+            1 => LatticeTemplate::Diamond,
+            2 => LatticeTemplate::TwoChains,
+            _ => LatticeTemplate::RandomDag(rng.gen_range(3..=6)),
+        }
+    }
+
+    /// The flows of a lattice of this shape, together with its classes.
+    pub fn generate<R: rand::Rng>(
+        self,
+        rng: &mut R,
+    ) -> (Vec<SecurityClass>, Vec<Flow<SecurityClass>>) {
+        let class = |c: &str| SecurityClass(c.to_string());
+        let flow = |from: &SecurityClass, into: &SecurityClass| Flow {
+            from: from.clone(),
+            into: into.clone(),
+        };
+
+        match self {
+            LatticeTemplate::Chain(n) => {
+                let classes = (0..n.max(1)).map(|i| class(&format!("L{i}"))).collect_vec();
+                let flows = classes
+                    .iter()
+                    .tuple_windows()
+                    .map(|(a, b)| flow(a, b))
+                    .collect();
+                (classes, flows)
+            }
+            LatticeTemplate::Diamond => {
+                let [bottom, left, right, top] = ["Bottom", "Left", "Right", "Top"].map(class);
+                let flows = vec![
+                    flow(&bottom, &left),
+                    flow(&bottom, &right),
+                    flow(&left, &top),
+                    flow(&right, &top),
+                ];
+                (vec![bottom, left, right, top], flows)
+            }
+            LatticeTemplate::TwoChains => {
+                let [public, internal, private, trusted, dubious] =
+                    ["Public", "Internal", "Private", "Trusted", "Dubious"].map(class);
+                let flows = vec![
+                    flow(&public, &internal),
+                    flow(&internal, &private),
+                    flow(&trusted, &dubious),
+                ];
+                (vec![private, internal, public, dubious, trusted], flows)
+            }
+            LatticeTemplate::RandomDag(k) => {
+                let classes = (0..k.max(1)).map(|i| class(&format!("C{i}"))).collect_vec();
+                // Every class flows into at least one later class, which keeps
+                // the graph connected, and flows only go forwards, which keeps
+                // it acyclic
+                let mut flows = vec![];
+                for (i, from) in classes.iter().enumerate() {
+                    let later = &classes[i + 1..];
+                    if later.is_empty() {
+                        break;
+                    }
+                    let first = rng.gen_range(0..later.len());
+                    for (j, into) in later.iter().enumerate() {
+                        if j == first || rng.gen_bool(0.3) {
+                            flows.push(flow(from, into));
+                        }
+                    }
+                }
+                (classes, flows)
+            }
+        }
+    }
+}
+
+impl SecurityAnalysisInput {
+    /// Generates an input with a lattice of the given shape, where every
+    /// target is classified with one of the classes of the lattice.
+    pub fn gen_with_template<R: rand::Rng>(
+        cx: &mut Commands,
+        rng: &mut R,
+        template: LatticeTemplate,
+    ) -> Self {
+        let (classes, flows) = template.generate(rng);
         let classification = Memory::from_targets_with(
             cx.fv(),
             rng,
             |rng, _| classes.choose(rng).unwrap().clone(),
             |rng, _| classes.choose(rng).unwrap().clone(),
         );
-        let lattice = SecurityLatticeInput(vec![
-            Flow {
-                from: public,
-                into: internal.clone(),
-            },
-            Flow {
-                from: internal,
-                into: private,
-            },
-            Flow {
-                from: trusted,
-                into: dubious,
-            },
-        ]);
 
         SecurityAnalysisInput {
             classification,
-            lattice,
+            lattice: SecurityLatticeInput(flows),
         }
     }
 }
 
+impl Generate for SecurityAnalysisInput {
+    type Context = Commands;
+
+    fn gen<R: rand::Rng>(cx: &mut Self::Context, rng: &mut R) -> Self {
+        let template = LatticeTemplate::random(rng);
+        Self::gen_with_template(cx, rng, template)
+    }
+}
+
 impl ToMarkdown for SecurityAnalysisInput {
     fn to_markdown(&self) -> Markdown {
         let mut table = comfy_table::Table::new();
@@ -88,7 +173,23 @@ impl ToMarkdown for SecurityAnalysisInput {
                 .to_string(),
         ]);
 
-        format!("{table}").into()
+        let flows = self
+            .lattice
+            .0
+            .iter()
+            .map(|f| format!("{} < {}", f.from, f.into))
+            .join(", ");
+        // NOTE: Markdown is compared between runs, so it leaves out the time
+        let lattice = DotDocument::new("lattice")
+            .header(&flows)
+            .timestamp(false)
+            .statements(
+                self.lattice
+                    .0
+                    .iter()
+                    .map(|f| format!("{:?} -> {:?};", f.from.0, f.into.0)),
+            );
+        format!("{table}\n\n```dot\n{lattice}\n```").into()
     }
 }
 
@@ -145,7 +246,36 @@ impl Environment for SecurityEnv {
 
     const ANALYSIS: Analysis = Analysis::Security;
 
+    fn validate_input(&self, cmds: &Commands, input: &Self::Input) -> Result<(), InputError> {
+        InputError::check_memory(cmds.fv(), &input.classification)?;
+
+        // NOTE: A lattice without flows is given by its classes alone, which
+        // only flow into themselves, so any class is accepted
+        let classes: HashSet<&SecurityClass> = input
+            .lattice
+            .0
+            .iter()
+            .flat_map(|f| [&f.from, &f.into])
+            .collect();
+        if classes.is_empty() {
+            return Ok(());
+        }
+        match input
+            .classification
+            .iter()
+            .find(|e| !classes.contains(e.value()))
+        {
+            Some(e) => Err(InputError::UnknownSecurityClass {
+                class: e.value().clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
     fn run(&self, cmds: &Commands, input: &Self::Input) -> Result<Self::Output, EnvError> {
+        self.validate_input(cmds, input)
+            .map_err(|err| EnvError::invalid_input::<Self>(input, err))?;
+
         let lattice = SecurityLattice::new(&input.lattice.0);
         Ok(SecurityAnalysisOutput::run(
             &input.classification,
@@ -154,6 +284,10 @@ impl Environment for SecurityEnv {
         ))
     }
 
+    fn equivalent_outputs(&self, a: &Self::Output, b: &Self::Output) -> bool {
+        a.is_equivalent(b)
+    }
+
     fn validate(
         &self,
         cmds: &Commands,
@@ -163,25 +297,9 @@ impl Environment for SecurityEnv {
     where
         Self::Output: PartialEq + std::fmt::Debug,
     {
-        fn stringify(flows: &[Flow<Target>]) -> Vec<Flow<&str>> {
-            let mut f = flows.iter().map(|f| f.map(|t| t.name())).collect_vec();
-            f.sort();
-            f
-        }
-
         let reference = self.run(cmds, input)?;
-        let reference_actual = stringify(&reference.actual);
-        let reference_allowed = stringify(&reference.allowed);
-        let reference_violations = stringify(&reference.violations);
-        let output = output.clone();
-        let output_actual = stringify(&output.actual);
-        let output_allowed = stringify(&output.allowed);
-        let output_violations = stringify(&output.violations);
-
-        if reference_actual == output_actual
-            && reference_allowed == output_allowed
-            && reference_violations == output_violations
-        {
+
+        if reference.is_equivalent(output) {
             Ok(ValidationResult::CorrectTerminated)
         } else {
             Ok(ValidationResult::Mismatch {
@@ -190,3 +308,66 @@ impl Environment for SecurityEnv {
         }
     }
 }
+
+#[test]
+fn diamond_lattice() {
+    use rand::SeedableRng;
+
+    use crate::security::SecurityLattice;
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let mut cmds = crate::parse::parse_commands("x := y; z := x").unwrap();
+    let input =
+        SecurityAnalysisInput::gen_with_template(&mut cmds, &mut rng, LatticeTemplate::Diamond);
+    let (classes, _) = LatticeTemplate::Diamond.generate(&mut rng);
+    assert!(input
+        .classification
+        .iter()
+        .all(|e| classes.contains(e.value())));
+
+    let lattice = SecurityLattice::new(&input.lattice.0);
+    let allows = |from: &str, into: &str| {
+        lattice.allows(&Flow {
+            from: SecurityClass(from.to_string()),
+            into: SecurityClass(into.to_string()),
+        })
+    };
+    for side in ["Left", "Right"] {
+        assert!(allows("Bottom", side));
+        assert!(allows(side, "Top"));
+        assert!(!allows(side, "Bottom"));
+    }
+    assert!(allows("Bottom", "Top"));
+    assert!(!allows("Left", "Right"));
+    assert!(!allows("Right", "Left"));
+}
+
+#[test]
+fn unknown_classes() {
+    use crate::parse::parse_commands;
+
+    let cmds = parse_commands("x := y").unwrap();
+    let class = |c: &str| SecurityClass(c.to_string());
+    let mut input = SecurityAnalysisInput {
+        classification: crate::sign::MemoryBuilder::new()
+            .var("x", class("Public"))
+            .var("y", class("Private"))
+            .build(),
+        lattice: SecurityLatticeInput(vec![Flow {
+            from: class("Public"),
+            into: class("Private"),
+        }]),
+    };
+    assert_eq!(SecurityEnv.validate_input(&cmds, &input), Ok(()));
+
+    input
+        .classification
+        .variables
+        .insert(crate::ast::Variable("y".to_string()), class("Secret"));
+    assert_eq!(
+        SecurityEnv.validate_input(&cmds, &input),
+        Err(InputError::UnknownSecurityClass {
+            class: class("Secret")
+        })
+    );
+}
@@ -1,6 +1,5 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet};
 
-use indexmap::IndexMap;
 use itertools::{chain, Itertools};
 
 use rand::seq::SliceRandom;
@@ -8,14 +7,14 @@ use serde::{Deserialize, Serialize};
 use tracing::error;
 
 use crate::{
-    analysis::{mono_analysis, FiFo, NodeOrder},
-    ast::{Commands, Target},
+    analysis::{mono_analysis, FiFo, MonotoneFramework, NodeOrder},
+    ast::{Array, Commands},
     generation::Generate,
-    pg::{Determinism, Node, ProgramGraph},
-    sign::{Memory, Sign, SignAnalysis, SignMemory, Signs},
+    pg::{Action, Determinism, Node, ProgramGraph},
+    sign::{LengthAbstraction, Memory, Sign, SignAnalysis, SignAnalysisReport, SignMemory, Signs},
 };
 
-use super::{Analysis, EnvError, Environment, Markdown, ToMarkdown, ValidationResult};
+use super::{Analysis, EnvError, Environment, InputError, Markdown, ToMarkdown, ValidationResult};
 
 #[derive(Debug)]
 pub struct SignEnv;
@@ -24,20 +23,90 @@ pub struct SignEnv;
 pub struct SignAnalysisInput {
     pub determinism: Determinism,
     pub assignment: SignMemory,
+    /// The lengths of the arrays in the assignment. Arrays without a length
+    /// are treated as having an unknown length.
+    #[serde(default)]
+    pub lengths: BTreeMap<Array, LengthAbstraction>,
+    /// Whether to check the loop invariants of the program against the
+    /// computed facts, and report which of them the signs can confirm.
+    #[serde(default)]
+    pub check_invariants: bool,
 }
 
 impl Generate for SignAnalysisInput {
     type Context = Commands;
 
     fn gen<R: rand::Rng>(cx: &mut Self::Context, rng: &mut R) -> Self {
+        let assignment = feasible_initial(cx, rng, 10);
+        // NOTE: An array needs at least one element of each of its signs, and
+        // only arrays without signs are empty
+        let lengths = assignment
+            .arrays
+            .iter()
+            .map(|(arr, signs)| {
+                let length = match signs.iter().count() as u64 {
+                    0 => 0,
+                    n => rng.gen_range(n..=n.max(5)),
+                };
+                (arr.clone(), LengthAbstraction::Known { length })
+            })
+            .collect();
         SignAnalysisInput {
             determinism: [Determinism::Deterministic, Determinism::NonDeterministic]
                 .choose(rng)
                 .copied()
                 .unwrap(),
-            assignment: Memory::gen(cx, rng),
+            assignment,
+            lengths,
+            check_invariants: false,
+        }
+    }
+}
+
+/// Generates an initial memory for which the first condition of the program
+/// can be taken, such that the analysis does not stop at the first branch.
+/// Up to `attempts` memories are generated, after which the last one is
+/// returned regardless.
+pub fn feasible_initial<R: rand::Rng>(
+    cx: &mut Commands,
+    rng: &mut R,
+    attempts: usize,
+) -> SignMemory {
+    let pg = ProgramGraph::new(Determinism::NonDeterministic, cx);
+    let mut assignment: SignMemory = Memory::gen(cx, rng);
+    for _ in 1..attempts {
+        if passes_first_branch(&pg, &assignment) {
+            break;
+        }
+        assignment = Memory::gen(cx, rng);
+    }
+    assignment
+}
+
+/// Follows the program graph from the start until the first branch, and checks
+/// that at least one of its edges can be taken from `assignment`.
+fn passes_first_branch(pg: &ProgramGraph, assignment: &SignMemory) -> bool {
+    let analysis = SignAnalysis {
+        assignment: assignment.clone(),
+        lengths: Default::default(),
+    };
+    let mut node = Node::Start;
+    let mut memories = analysis.initial(pg);
+    for _ in 0..pg.nodes().len() {
+        match pg.outgoing(node) {
+            [] => break,
+            [e] if !matches!(e.action(), Action::Condition(_)) => {
+                memories = analysis.semantic(pg, e, &memories);
+                node = e.to();
+            }
+            edges => {
+                return edges
+                    .iter()
+                    .any(|e| !analysis.semantic(pg, e, &memories).is_empty())
+            }
         }
     }
+    !memories.is_empty()
 }
 
 impl ToMarkdown for SignAnalysisInput {
@@ -47,13 +116,7 @@ impl ToMarkdown for SignAnalysisInput {
             .load_preset(comfy_table::presets::ASCII_MARKDOWN)
             .set_header(["Input"]);
 
-        table.add_row([
-            "Determinism:",
-            match self.determinism {
-                Determinism::Deterministic => "**✓**",
-                Determinism::NonDeterministic => "**✕**",
-            },
-        ]);
+        table.add_row(["Determinism:".to_string(), self.determinism.to_string()]);
 
         table.add_row([
             "Memory:".to_string(),
@@ -64,6 +127,20 @@ impl ToMarkdown for SignAnalysisInput {
                 .to_string(),
         ]);
 
+        if !self.lengths.is_empty() {
+            table.add_row([
+                "Lengths:".to_string(),
+                self.lengths
+                    .iter()
+                    .map(|(arr, length)| match length {
+                        LengthAbstraction::Known { length } => format!("`{arr} = {length}`"),
+                        LengthAbstraction::Unknown => format!("`{arr} = ?`"),
+                    })
+                    .format(", ")
+                    .to_string(),
+            ]);
+        }
+
         format!("{table}").into()
     }
 }
@@ -89,69 +166,117 @@ impl Generate for Signs {
 pub struct SignAnalysisOutput {
     pub initial_node: String,
     pub final_node: String,
-    pub nodes: IndexMap<String, HashSet<SignMemory>>,
+    /// The abstract memories reaching each node, keyed by the name of the node
+    pub nodes: BTreeMap<String, BTreeSet<SignMemory>>,
+    /// The number of times the semantics of an edge was computed before the
+    /// fixed point was reached
+    #[serde(default)]
+    pub iterations: u64,
+    /// The potential problems found by the analysis, see
+    /// [`SignWarning`](crate::sign::SignWarning)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Whether the facts describe every execution, see
+    /// [`SignAnalysisReport::is_sound`]
+    #[serde(default = "sound")]
+    pub is_sound: bool,
+    /// The verdicts on the loop invariants, when they were asked for with
+    /// [`SignAnalysisInput::check_invariants`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub invariants: Vec<String>,
+}
+
+fn sound() -> bool {
+    true
 }
 
 impl ToMarkdown for SignAnalysisOutput {
     fn to_markdown(&self) -> Markdown {
-        let variables: HashSet<_> = self
+        let variables: BTreeSet<_> = self
             .nodes
-            .iter()
-            .flat_map(|(_, worlds)| worlds.iter().flat_map(|w| w.variables.keys().cloned()))
+            .values()
+            .flat_map(|worlds| worlds.iter().flat_map(|w| w.variables.keys().cloned()))
             .collect();
-        let arrays: HashSet<_> = self
+        let arrays: BTreeSet<_> = self
             .nodes
-            .iter()
-            .flat_map(|(_, worlds)| worlds.iter().flat_map(|w| w.arrays.keys().cloned()))
+            .values()
+            .flat_map(|worlds| worlds.iter().flat_map(|w| w.arrays.keys().cloned()))
             .collect();
-        let variables = variables.into_iter().sorted().collect_vec();
-        let arrays = arrays.into_iter().sorted().collect_vec();
-
-        let mut table = comfy_table::Table::new();
-        table
-            .load_preset(comfy_table::presets::ASCII_MARKDOWN)
-            .set_header(chain!(
-                ["Node".to_string()],
-                variables.iter().map(|v| v.to_string()),
-                arrays.iter().map(|v| v.to_string())
-            ));
 
-        for (n, worlds) in self
+        let facts = self
             .nodes
             .iter()
             .sorted_by_key(|(n, _)| NodeOrder::parse(n))
-        {
-            let mut first = true;
-            for w in worlds {
-                let is_first = first;
-                first = false;
-
-                table.add_row(chain!(
-                    [if is_first {
-                        n.to_string()
-                    } else {
-                        "".to_string()
-                    }],
-                    variables.iter().map(|var| {
-                        w.variables
-                            .get(var)
+            .map(|(n, worlds)| {
+                if worlds.is_empty() {
+                    return format!("**{n}**\n\nNo memories reach this node.");
+                }
+
+                let mut table = comfy_table::Table::new();
+                table
+                    .load_preset(comfy_table::presets::ASCII_MARKDOWN)
+                    .set_header(chain!(
+                        variables.iter().map(|v| v.to_string()),
+                        arrays.iter().map(|v| v.to_string())
+                    ));
+                for w in worlds {
+                    table.add_row(chain!(
+                        variables.iter().map(|var| {
+                            w.variables
+                                .get(var)
+                                .cloned()
+                                .unwrap_or_default()
+                                .to_string()
+                        }),
+                        arrays.iter().map(|arr| w
+                            .arrays
+                            .get(arr)
                             .cloned()
                             .unwrap_or_default()
-                            .to_string()
-                    }),
-                    arrays.iter().map(|arr| w
-                        .arrays
-                        .get(arr)
-                        .cloned()
-                        .unwrap_or_default()
-                        .to_string()),
-                ));
-            }
-            if worlds.is_empty() {
-                table.add_row([n.to_string()]);
-            }
+                            .to_string()),
+                    ));
+                }
+                format!("**{n}**\n\n{table}")
+            })
+            .join("\n\n");
+
+        let mut md = facts;
+        if !self.warnings.is_empty() {
+            md += "\n\n**Warnings**\n\n";
+            md += &self.warnings.iter().map(|w| format!("- {w}")).join("\n");
         }
-        format!("{table}").into()
+        if !self.is_sound {
+            md += "\n\n**The facts might miss executions which divide by zero.**";
+        }
+        if !self.invariants.is_empty() {
+            md += "\n\n**Invariants**\n\n";
+            md += &self.invariants.iter().map(|c| format!("- {c}")).join("\n");
+        }
+        md.into()
+    }
+}
+
+impl SignAnalysisOutput {
+    fn from_report(report: SignAnalysisReport, iterations: u64) -> SignAnalysisOutput {
+        SignAnalysisOutput {
+            initial_node: Node::Start.to_string(),
+            final_node: Node::End.to_string(),
+            nodes: report
+                .per_node
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.into_iter().collect()))
+                .collect(),
+            iterations,
+            warnings: report.warnings.iter().map(|w| w.to_string()).collect(),
+            is_sound: report.is_sound,
+            invariants: report.invariants.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+}
+
+impl ToMarkdown for SignAnalysisReport {
+    fn to_markdown(&self) -> Markdown {
+        SignAnalysisOutput::from_report(self.clone(), 0).to_markdown()
     }
 }
 
@@ -162,44 +287,42 @@ impl Environment for SignEnv {
 
     const ANALYSIS: Analysis = Analysis::Sign;
 
-    fn run(&self, cmds: &Commands, input: &Self::Input) -> Result<Self::Output, EnvError> {
-        let pg = ProgramGraph::new(input.determinism, cmds);
-
-        for t in pg.fv() {
-            match t {
-                Target::Variable(var) => {
-                    if input.assignment.get_var(&var).is_none() {
-                        return Err(EnvError::InvalidInputForProgram {
-                            input: super::Input::from_concrete::<Self>(input),
-                            message: format!("variable `{var}` was not in the given input"),
-                        });
-                    }
-                }
-                Target::Array(arr, _) => {
-                    if input.assignment.get_arr(&arr).is_none() {
-                        return Err(EnvError::InvalidInputForProgram {
-                            input: super::Input::from_concrete::<Self>(input),
-                            message: format!("array `{arr}` was not in the given input"),
-                        });
-                    }
+    fn validate_input(&self, cmds: &Commands, input: &Self::Input) -> Result<(), InputError> {
+        InputError::check_determinism(cmds, input.determinism)?;
+        InputError::check_memory(cmds.fv(), &input.assignment)?;
+        for (arr, length) in &input.lengths {
+            let Some(signs) = input.assignment.get_arr(arr) else {
+                return Err(InputError::UnknownArray { arr: arr.clone() });
+            };
+            // Only empty arrays have no signs
+            if let LengthAbstraction::Known { length } = length {
+                if (*length == 0) != signs.is_empty() {
+                    return Err(InputError::InconsistentMemorySize);
                 }
             }
         }
+        Ok(())
+    }
 
-        Ok(SignAnalysisOutput {
-            initial_node: Node::Start.to_string(),
-            final_node: Node::End.to_string(),
-            nodes: mono_analysis::<_, FiFo>(
-                SignAnalysis {
-                    assignment: input.assignment.clone(),
-                },
-                &pg,
-            )
-            .facts
-            .into_iter()
-            .map(|(k, v)| (format!("{k}"), v))
-            .collect(),
-        })
+    fn run(&self, cmds: &Commands, input: &Self::Input) -> Result<Self::Output, EnvError> {
+        self.validate_input(cmds, input)
+            .map_err(|err| EnvError::invalid_input::<Self>(input, err))?;
+
+        let pg = ProgramGraph::new(input.determinism, cmds);
+        let analysis = SignAnalysis {
+            assignment: input.assignment.clone(),
+            lengths: input.lengths.clone(),
+        };
+        let result = mono_analysis::<_, FiFo>(analysis.clone(), &pg);
+        let mut report = analysis.report(&pg, result.facts);
+        if input.check_invariants {
+            report.check_invariants(&pg);
+        }
+
+        Ok(SignAnalysisOutput::from_report(
+            report,
+            result.semantic_calls as u64,
+        ))
     }
 
     fn validate(
@@ -213,28 +336,211 @@ impl Environment for SignEnv {
     {
         let reference = self.run(cmds, input)?;
 
-        let mut pool = reference.nodes.values().collect_vec();
-
-        for (n, o) in &output.nodes {
-            if let Some(idx) = pool.iter().position(|r| *r == o) {
-                pool.remove(idx);
-            } else {
-                error!(not_in_reference = format!("{o:?}"), "damn...");
+        for (n, worlds) in &reference.nodes {
+            let Some(output_worlds) = output.nodes.get(n) else {
+                error!(node = n, "missing node");
+                return Ok(ValidationResult::Mismatch {
+                    reason: format!("The node {n} was missing from the output"),
+                });
+            };
+            if let Some(w) = output_worlds.difference(worlds).next() {
+                error!(not_in_reference = format!("{w:?}"), node = n, "damn...");
+                return Ok(ValidationResult::Mismatch {
+                    reason: format!(
+                        "Produced world which did not exist in reference: {n} ~> {}",
+                        w.iter().format(", ")
+                    ),
+                });
+            }
+            if let Some(w) = worlds.difference(output_worlds).next() {
+                error!(missing = format!("{w:?}"), node = n, "oh no...");
                 return Ok(ValidationResult::Mismatch {
                     reason: format!(
-                        "Produced world which did not exist in reference: {n:?} ~> {o:?}"
+                        "Reference had world which was not present: {n} ~> {}",
+                        w.iter().format(", ")
                     ),
                 });
             }
         }
-
-        if pool.is_empty() {
-            Ok(ValidationResult::CorrectTerminated)
-        } else {
-            error!(missing = format!("{pool:?}"), "oh no...");
-            Ok(ValidationResult::Mismatch {
-                reason: "Reference had world which was not present".to_string(),
-            })
+        if let Some(n) = output
+            .nodes
+            .keys()
+            .find(|n| !reference.nodes.contains_key(*n))
+        {
+            return Ok(ValidationResult::Mismatch {
+                reason: format!("The node {n} does not exist in the reference"),
+            });
         }
+
+        Ok(ValidationResult::CorrectTerminated)
+    }
+}
+
+#[test]
+fn sign_output() {
+    use crate::{parse::parse_commands, sign::MemoryBuilder};
+
+    let cmds = parse_commands("x := x - 1; if x > 0 -> y := 1 [] x <= 0 -> y := -1 fi").unwrap();
+    let input = SignAnalysisInput {
+        determinism: Determinism::NonDeterministic,
+        assignment: MemoryBuilder::new()
+            .var("x", Sign::Positive)
+            .var("y", Sign::Zero)
+            .build(),
+        lengths: Default::default(),
+        check_invariants: false,
+    };
+    let output = SignEnv.run(&cmds, &input).unwrap();
+    assert!(output.is_sound);
+    assert_eq!(
+        output.to_markdown().to_string(),
+        "**q▷**\n\n| x | y |\n|---|---|\n| + | 0 |\n\n\
+         **q1**\n\n| x | y |\n|---|---|\n| + | 0 |\n| 0 | 0 |\n| - | 0 |\n\n\
+         **q2**\n\n| x | y |\n|---|---|\n| 0 | 0 |\n| - | 0 |\n\n\
+         **q3**\n\n| x | y |\n|---|---|\n| + | 0 |\n\n\
+         **q◀**\n\n| x | y |\n|---|---|\n| + | + |\n| 0 | - |\n| - | - |\n\n\
+         **Warnings**\n\n\
+         - `x` can have any sign in q1\n\
+         - `x` can have any sign in q◀"
+    );
+
+    // The order of the memories does not matter, but every node must match
+    let mut json = serde_json::to_value(&output).unwrap();
+    json["nodes"]["q◀"].as_array_mut().unwrap().reverse();
+    let reordered = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(
+        SignEnv.validate(&cmds, &input, &reordered).unwrap(),
+        ValidationResult::CorrectTerminated
+    );
+
+    json["nodes"]["q2"].as_array_mut().unwrap().pop();
+    let missing = serde_json::from_value(json).unwrap();
+    assert!(matches!(
+        SignEnv.validate(&cmds, &input, &missing).unwrap(),
+        ValidationResult::Mismatch { .. }
+    ));
+}
+
+#[test]
+fn sign_output_warnings() {
+    use crate::{parse::parse_commands, sign::MemoryBuilder};
+
+    let cmds = parse_commands("y := 1 / x").unwrap();
+    let input = SignAnalysisInput {
+        determinism: Determinism::Deterministic,
+        assignment: MemoryBuilder::new()
+            .var("x", Sign::Zero)
+            .var("y", Sign::Zero)
+            .build(),
+        lengths: Default::default(),
+        check_invariants: false,
+    };
+    let output = SignEnv.run(&cmds, &input).unwrap();
+    assert!(!output.is_sound);
+    assert_eq!(output.warnings[0], "`(1 / x)` might divide by zero in q▷");
+
+    let md = output.to_markdown().to_string();
+    assert!(md.contains("**Warnings**\n\n- `(1 / x)` might divide by zero in q▷"));
+    assert!(md.ends_with("**The facts might miss executions which divide by zero.**"));
+}
+
+#[test]
+fn invariant_checks() {
+    use crate::{parse::parse_commands, sign::MemoryBuilder};
+
+    let cmds =
+        parse_commands("{ true } x := 1; do { x > 0 } x < 10 -> x := x + 1 od { true }").unwrap();
+    let mut input = SignAnalysisInput {
+        determinism: Determinism::Deterministic,
+        assignment: MemoryBuilder::new().var("x", Sign::Zero).build(),
+        lengths: Default::default(),
+        check_invariants: false,
+    };
+    assert!(SignEnv.run(&cmds, &input).unwrap().invariants.is_empty());
+
+    input.check_invariants = true;
+    let output = SignEnv.run(&cmds, &input).unwrap();
+    assert_eq!(
+        output.invariants,
+        ["invariant `(x > 0)` in q1: verified abstractly"]
+    );
+    assert!(output
+        .to_markdown()
+        .ends_with("**Invariants**\n\n- invariant `(x > 0)` in q1: verified abstractly"));
+}
+
+#[test]
+fn known_array_lengths() {
+    use crate::{parse::parse_commands, sign::MemoryBuilder};
+
+    let cmds = parse_commands("A[i] := 1; A[2] := -1").unwrap();
+    let mut input = SignAnalysisInput {
+        determinism: Determinism::Deterministic,
+        assignment: MemoryBuilder::new()
+            .var("i", Sign::Zero)
+            .array("A", Signs::ZERO)
+            .build(),
+        lengths: [(
+            Array("A".to_string()),
+            LengthAbstraction::Known { length: 2 },
+        )]
+        .into_iter()
+        .collect(),
+        check_invariants: false,
+    };
+    let pg = ProgramGraph::new(input.determinism, &cmds);
+    let analysis = SignAnalysis {
+        assignment: input.assignment.clone(),
+        lengths: input.lengths.clone(),
+    };
+    assert_eq!(
+        analysis.definitely_out_of_bounds(&pg),
+        vec![pg.edges()[1].clone()]
+    );
+
+    // NOTE: Constant indices out of bounds are only warned about, as the
+    // lengths are not part of what implementations are given
+    let output = SignEnv.run(&cmds, &input).unwrap();
+    assert_eq!(output.nodes["q1"].len(), 2);
+    assert_eq!(output.warnings, ["`A[2]` is out of bounds in q1"]);
+    assert!(output
+        .to_markdown()
+        .to_string()
+        .contains("- `A[2]` is out of bounds in q1"));
+    assert!(!output.nodes[&output.final_node].is_empty());
+
+    let mut empty = input.clone();
+    empty.assignment = MemoryBuilder::new()
+        .var("i", Sign::Zero)
+        .array("A", Signs::NONE)
+        .build();
+    empty.lengths.insert(
+        Array("A".to_string()),
+        LengthAbstraction::Known { length: 0 },
+    );
+    let output = SignEnv.run(&cmds, &empty).unwrap();
+    assert!(output.nodes["q1"].is_empty());
+
+    input.lengths.insert(
+        Array("A".to_string()),
+        LengthAbstraction::Known { length: 0 },
+    );
+    assert_eq!(
+        SignEnv.validate_input(&cmds, &input),
+        Err(InputError::InconsistentMemorySize)
+    );
+}
+
+#[test]
+fn feasible_initial_memories() {
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use crate::{ast::Variable, parse::parse_commands};
+
+    let mut cmds = parse_commands("y := 1; if x > 0 -> y := x fi").unwrap();
+    let mut rng = SmallRng::seed_from_u64(0);
+    for _ in 0..20 {
+        let memory = feasible_initial(&mut cmds, &mut rng, 50);
+        assert_eq!(memory.variables[&Variable("x".to_string())], Sign::Positive);
     }
 }
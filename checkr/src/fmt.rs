@@ -3,8 +3,8 @@ use std::fmt::Display;
 use itertools::Itertools;
 
 use crate::ast::{
-    AExpr, AOp, Array, BExpr, Command, Commands, Function, Guard, LogicOp, Quantifier, RelOp,
-    Target, Variable,
+    AExpr, AOp, Array, BExpr, CastTarget, Command, Commands, Function, Guard, LogicOp, Quantifier,
+    RelOp, Target, Variable,
 };
 
 impl Display for Variable {
@@ -41,13 +41,24 @@ impl Display for Command {
             Command::Assignment(target, expr) => write!(f, "{target} := {expr}"),
             Command::If(guards) => write!(f, "if {}\nfi", guards.iter().format("\n[] ")),
             Command::Loop(guards) => write!(f, "do {}\nod", guards.iter().format("\n[] ")),
-            Command::EnrichedLoop(pred, guards) => {
+            Command::EnrichedLoop(pred, None, guards) => {
                 write!(f, "do {{{pred}}}\n   {}\nod", guards.iter().format("\n[] "))
             }
+            Command::EnrichedLoop(pred, Some(variant), guards) => write!(
+                f,
+                "do [inv: {pred}; var: {variant}]\n   {}\nod",
+                guards.iter().format("\n[] ")
+            ),
             Command::Annotated(p, c, q) => write!(f, "{{{p}}}\n{c}\n{{{q}}}"),
             Command::Break => write!(f, "break"),
             Command::Continue => write!(f, "continue"),
+            Command::For(i, from, to, c) => write!(f, "for {i} in {from}..{to} do\n{c}\ndone"),
             Command::Skip => write!(f, "skip"),
+            Command::Choice(alternatives) => {
+                write!(f, "(\n{}\n)", alternatives.iter().format("\n[]\n"))
+            }
+            Command::Precondition(b) => write!(f, "precondition {b}"),
+            Command::Postcondition(b) => write!(f, "postcondition {b}"),
         }
     }
 }
@@ -81,6 +92,16 @@ impl Display for AExpr {
             AExpr::Binary(l, op, r) => write!(f, "({l} {op} {r})"),
             AExpr::Minus(m) => write!(f, "-{m}"),
             AExpr::Function(fun) => write!(f, "{fun}"),
+            AExpr::Cast(x, t) => write!(f, "({x} as {t})"),
+        }
+    }
+}
+impl Display for CastTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CastTarget::I32 => write!(f, "i32"),
+            CastTarget::U32 => write!(f, "u32"),
+            CastTarget::U64 => write!(f, "u64"),
         }
     }
 }
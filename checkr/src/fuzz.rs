@@ -0,0 +1,262 @@
+//! Differential testing of two implementations against each other, for
+//! validating a reference implementation without trusting either side.
+
+use std::{mem::discriminant, ops::Range, time::Duration};
+
+use crate::{
+    ast::Commands,
+    driver::Driver,
+    env::{Analysis, Input, Output, ValidationResult},
+    shrink, ProgramGenerationBuilder,
+};
+
+/// A generated program on which two implementations disagree.
+#[derive(Debug)]
+pub struct Discrepancy {
+    pub seed: u64,
+    pub cmds: Commands,
+    /// A smaller version of `cmds` which shows the same kind of discrepancy on
+    /// the same input, see [`shrink::candidates`]
+    pub shrunk: Commands,
+    pub input: Input,
+    pub kind: DiscrepancyKind,
+}
+
+#[derive(Debug)]
+pub enum DiscrepancyKind {
+    /// Neither implementation produced an output. This is not a disagreement,
+    /// but is reported since it hides whether the two would agree.
+    BothFailed { a: String, b: String },
+    /// Only one of the implementations produced an output
+    OnlyOneSucceeded {
+        a: Result<Output, String>,
+        b: Result<Output, String>,
+    },
+    /// Both implementations produced an output, but they are not equivalent.
+    /// The verdicts of validating each output with the built-in reference are
+    /// included to tell which side is likely at fault.
+    DifferentOutputs {
+        a: Output,
+        b: Output,
+        a_verdict: Result<ValidationResult, String>,
+        b_verdict: Result<ValidationResult, String>,
+    },
+}
+
+/// Runs the programs generated by `builder` for each of the `seeds` on both
+/// drivers, and collects the ones where their outputs are not
+/// [equivalent](crate::env::Environment::equivalent_outputs), such that
+/// listing the same facts in another order is not a discrepancy. Runs which
+/// exceed `timeout` count as failures, and programs on which both drivers
+/// fail are collected as well.
+///
+/// Each discrepancy is shrunk greedily, by repeatedly taking the first
+/// smaller program on which the drivers show the same kind of discrepancy.
+pub async fn fuzz_compare(
+    analysis: Analysis,
+    driver_a: &Driver,
+    driver_b: &Driver,
+    seeds: Range<u64>,
+    builder: &ProgramGenerationBuilder,
+    timeout: Duration,
+) -> Vec<Discrepancy> {
+    let mut discrepancies = vec![];
+
+    for seed in seeds {
+        let generated = ProgramGenerationBuilder {
+            analysis,
+            ..builder.clone()
+        }
+        .seed(Some(seed))
+        .build();
+        let Some(kind) = compare(
+            analysis,
+            [driver_a, driver_b],
+            &generated.cmds,
+            &generated.input,
+            timeout,
+        )
+        .await
+        else {
+            continue;
+        };
+
+        let mut shrunk = generated.cmds.clone();
+        'shrink: loop {
+            for candidate in shrink::candidates(&shrunk) {
+                let again = compare(
+                    analysis,
+                    [driver_a, driver_b],
+                    &candidate,
+                    &generated.input,
+                    timeout,
+                )
+                .await;
+                if again.is_some_and(|k| discriminant(&k) == discriminant(&kind)) {
+                    shrunk = candidate;
+                    continue 'shrink;
+                }
+            }
+            break;
+        }
+
+        discrepancies.push(Discrepancy {
+            seed,
+            cmds: generated.cmds,
+            shrunk,
+            input: generated.input,
+            kind,
+        });
+    }
+
+    discrepancies
+}
+
+/// Runs `cmds` on both drivers and compares their outputs, returning `None`
+/// if they agree.
+async fn compare(
+    analysis: Analysis,
+    [driver_a, driver_b]: [&Driver; 2],
+    cmds: &Commands,
+    input: &Input,
+    timeout: Duration,
+) -> Option<DiscrepancyKind> {
+    let cmds_src = cmds.to_string();
+    let input_src = input.to_string();
+
+    let a = run(driver_a, analysis, &cmds_src, &input_src, timeout).await;
+    let b = run(driver_b, analysis, &cmds_src, &input_src, timeout).await;
+
+    Some(match (a, b) {
+        (Err(a), Err(b)) => DiscrepancyKind::BothFailed { a, b },
+        (Ok(a), Ok(b)) if equivalent(analysis, &a, &b) => return None,
+        (Ok(a), Ok(b)) => {
+            let verdict = |output: &Output| {
+                analysis
+                    .validate(cmds, input.clone(), output.clone())
+                    .map_err(|err| err.to_string())
+            };
+            DiscrepancyKind::DifferentOutputs {
+                a_verdict: verdict(&a),
+                b_verdict: verdict(&b),
+                a,
+                b,
+            }
+        }
+        (a, b) => DiscrepancyKind::OnlyOneSucceeded { a, b },
+    })
+}
+
+/// Outputs which are not valid for `analysis` are compared as they are.
+fn equivalent(analysis: Analysis, a: &Output, b: &Output) -> bool {
+    analysis
+        .equivalent_outputs(a.clone(), b.clone())
+        .unwrap_or(a == b)
+}
+
+async fn run(
+    driver: &Driver,
+    analysis: Analysis,
+    cmds: &str,
+    input: &str,
+    timeout: Duration,
+) -> Result<Output, String> {
+    match tokio::time::timeout(timeout, driver.exec_dyn_raw_cmds(analysis, cmds, input)).await {
+        Ok(Ok(output)) => Ok(output.parsed),
+        Ok(Err(err)) => Err(err.to_string()),
+        Err(_) => Err(format!("timed out after {timeout:?}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{fuzz_compare, DiscrepancyKind};
+    use crate::{driver::Driver, env::Analysis, tests::unique_temp_dir, ProgramGenerationBuilder};
+
+    fn driver(name: &str, output: &str) -> Driver {
+        let dir = unique_temp_dir(&format!("fuzz-{name}"));
+        std::fs::write(dir.join("run.sh"), format!("echo '{output}'\n")).unwrap();
+        Driver::new(&dir, "sh run.sh")
+    }
+
+    #[tokio::test]
+    async fn differential() {
+        let reference = driver("reference", "\"skip\"");
+        let copy = driver("copy", "\"skip\"");
+        let buggy = driver("buggy", "\"abort\"");
+        let builder = ProgramGenerationBuilder::new(Analysis::Parse);
+        let timeout = Duration::from_secs(5);
+
+        let same = fuzz_compare(Analysis::Parse, &reference, &copy, 0..3, &builder, timeout).await;
+        assert!(same.is_empty());
+
+        let different =
+            fuzz_compare(Analysis::Parse, &reference, &buggy, 0..3, &builder, timeout).await;
+        assert_eq!(
+            different.iter().map(|d| d.seed).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        assert!(matches!(
+            different[0].kind,
+            DiscrepancyKind::DifferentOutputs { .. }
+        ));
+        // The buggy driver disagrees on every program, so a single command is
+        // enough to show it
+        for d in &different {
+            assert_eq!(d.shrunk.0.len(), 1, "{} was not shrunk", d.cmds);
+        }
+    }
+
+    #[tokio::test]
+    async fn both_failing() {
+        let failing = |name: &str| {
+            let dir = unique_temp_dir(&format!("fuzz-{name}"));
+            std::fs::write(dir.join("run.sh"), "exit 1\n").unwrap();
+            Driver::new(&dir, "sh run.sh")
+        };
+        let builder = ProgramGenerationBuilder::new(Analysis::Parse);
+        let timeout = Duration::from_secs(5);
+
+        let failed = fuzz_compare(
+            Analysis::Parse,
+            &failing("a"),
+            &failing("b"),
+            0..2,
+            &builder,
+            timeout,
+        )
+        .await;
+        assert_eq!(failed.len(), 2);
+        assert!(failed
+            .iter()
+            .all(|d| matches!(d.kind, DiscrepancyKind::BothFailed { .. })));
+    }
+
+    #[tokio::test]
+    async fn reordered_facts() {
+        let flows = |first: &str, second: &str| {
+            format!(r#"{{ "actual": [{first}, {second}], "allowed": [], "violations": [] }}"#)
+        };
+        let (ab, bc) = (
+            r#"{ "from": "a", "into": "b" }"#,
+            r#"{ "from": "b", "into": "c" }"#,
+        );
+        let forwards = driver("forwards", &flows(ab, bc));
+        let backwards = driver("backwards", &flows(bc, ab));
+        let builder = ProgramGenerationBuilder::new(Analysis::Security);
+        let timeout = Duration::from_secs(5);
+
+        let same = fuzz_compare(
+            Analysis::Security,
+            &forwards,
+            &backwards,
+            0..3,
+            &builder,
+            timeout,
+        )
+        .await;
+        assert!(same.is_empty());
+    }
+}
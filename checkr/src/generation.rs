@@ -127,7 +127,7 @@ pub fn annotate_cmds<R: Rng>(mut cmds: Commands, rng: &mut R) -> Command {
         },
         sign::{Memory, Sign, Signs},
     };
-    use std::collections::HashSet;
+    use std::collections::BTreeSet;
 
     let input = SignAnalysisInput::gen(&mut cmds, rng);
     let sign_result = SignEnv
@@ -139,7 +139,7 @@ pub fn annotate_cmds<R: Rng>(mut cmds: Commands, rng: &mut R) -> Command {
 
     return Command::Annotated(pre, cmds, post);
 
-    fn signs_in(assignment: &HashSet<Memory<Sign, Signs>>) -> BExpr {
+    fn signs_in(assignment: &BTreeSet<Memory<Sign, Signs>>) -> BExpr {
         assignment
             .iter()
             .filter_map(|world| {
@@ -1,8 +1,19 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ops::RangeInclusive,
+};
+
+use itertools::{chain, Itertools};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 
 use crate::{
-    ast::{AExpr, AOp, BExpr, Function, Int, LogicOp, RelOp, Target},
-    pg::{Action, Node, ProgramGraph},
+    ast::{
+        AExpr, AOp, Array, BExpr, CastTarget, Command, Commands, Function, Int, LogicOp, RelOp,
+        Target, Variable,
+    },
+    pg::{can_reach_end_within, Action, Determinism, Node, ProgramGraph},
     sign::Memory,
 };
 
@@ -14,6 +25,85 @@ impl InterpreterMemory {
     pub fn zero(pg: &ProgramGraph) -> InterpreterMemory {
         Memory::from_targets(pg.fv(), |_| 0, |_| vec![])
     }
+    /// A memory where every variable of `cmds` is zero and every array
+    /// contains `array_len` zeros.
+    pub fn zeroed_for(cmds: &Commands, array_len: usize) -> InterpreterMemory {
+        Memory::from_targets(cmds.fv(), |_| 0, |_| vec![0; array_len])
+    }
+    /// A memory for the free variables of `pg`, which are zero unless they are
+    /// given a value in `overrides`, and arrays which contain `array_len` zeros
+    /// unless they are given in `array_overrides`. Overrides of names which are
+    /// not in `pg` are ignored with a warning.
+    pub fn from_partial(
+        pg: &ProgramGraph,
+        overrides: &HashMap<String, Int>,
+        array_overrides: &HashMap<String, Vec<Int>>,
+        array_len: usize,
+    ) -> InterpreterMemory {
+        let fv = pg.fv();
+        for name in overrides.keys() {
+            if !fv.contains(&Target::Variable(Variable(name.clone()))) {
+                warn!(name, "ignoring the initial value of an unknown variable");
+            }
+        }
+        for name in array_overrides.keys() {
+            if !fv.contains(&Target::Array(Array(name.clone()), ())) {
+                warn!(name, "ignoring the initial value of an unknown array");
+            }
+        }
+
+        Memory::from_targets(
+            fv,
+            |var| overrides.get(&var.0).copied().unwrap_or(0),
+            |arr| {
+                array_overrides
+                    .get(&arr.0)
+                    .cloned()
+                    .unwrap_or_else(|| vec![0; array_len])
+            },
+        )
+    }
+}
+
+/// Ranges of the initial values of variables. Variables without a bound in
+/// either direction lie within [`InterpreterBounds::DEFAULT_WIDTH`] of the
+/// other bound, or in `-10..=10` if they have no bounds at all.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct InterpreterBounds {
+    pub min_values: HashMap<Variable, i64>,
+    pub max_values: HashMap<Variable, i64>,
+}
+
+impl InterpreterBounds {
+    pub const DEFAULT_WIDTH: i64 = 20;
+
+    pub fn range(&self, var: &Variable) -> RangeInclusive<i64> {
+        match (self.min_values.get(var), self.max_values.get(var)) {
+            (Some(&min), Some(&max)) => min.min(max)..=max.max(min),
+            (Some(&min), None) => min..=min.saturating_add(Self::DEFAULT_WIDTH),
+            (None, Some(&max)) => max.saturating_sub(Self::DEFAULT_WIDTH)..=max,
+            (None, None) => -Self::DEFAULT_WIDTH / 2..=Self::DEFAULT_WIDTH / 2,
+        }
+    }
+}
+
+/// Samples a memory containing every variable mentioned in `bounds`, with
+/// values in their respective ranges.
+pub fn sample_memory<R: Rng>(bounds: &InterpreterBounds, rng: &mut R) -> InterpreterMemory {
+    // NOTE: The variables are sampled in a fixed order, such that the memory
+    // only depends on the seed of `rng`
+    let vars: BTreeSet<&Variable> = bounds
+        .min_values
+        .keys()
+        .chain(bounds.max_values.keys())
+        .collect();
+    Memory {
+        variables: vars
+            .into_iter()
+            .map(|var| (var.clone(), rng.gen_range(bounds.range(var))))
+            .collect(),
+        arrays: BTreeMap::new(),
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -22,6 +112,27 @@ pub enum TerminationState {
     Running,
     Stuck,
     Terminated,
+    /// The initial memory exceeds the [`InterpreterLimits`] of the execution
+    MemoryLimitExceeded,
+}
+
+/// Bounds on the size of the memory of an execution. An execution whose
+/// initial memory exceeds a limit stops immediately in
+/// [`TerminationState::MemoryLimitExceeded`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterpreterLimits {
+    /// The maximum total number of elements across all arrays
+    pub max_array_element_count: Option<usize>,
+    /// The maximum number of variables
+    pub max_variable_count: Option<usize>,
+}
+
+impl InterpreterLimits {
+    pub fn exceeded_by(&self, memory: &InterpreterMemory) -> bool {
+        let elements: usize = memory.arrays.values().map(|values| values.len()).sum();
+        matches!(self.max_array_element_count, Some(max) if elements > max)
+            || matches!(self.max_variable_count, Some(max) if memory.variables.len() > max)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -39,12 +150,98 @@ impl<A> Configuration<A> {
     }
 }
 
+/// Renders a trace as CSV with a column for the node and one for each
+/// variable. Arrays are flattened into a column per index, `A[0]`, `A[1]`,
+/// ..., with as many columns as the longest value of the array in the trace.
+/// Configurations where an array is shorter leave the remaining cells empty,
+/// such that columns stay aligned even if the length of an array changes.
+pub fn trace_to_csv<N: std::fmt::Display>(trace: &[Configuration<N>]) -> String {
+    let variables: BTreeSet<&Variable> = trace
+        .iter()
+        .flat_map(|c| c.memory.variables.keys())
+        .collect();
+    let mut arrays: BTreeMap<&Array, usize> = BTreeMap::new();
+    for (arr, values) in trace.iter().flat_map(|c| &c.memory.arrays) {
+        let len = arrays.entry(arr).or_default();
+        *len = (*len).max(values.len());
+    }
+
+    let header = chain!(
+        ["node".to_string()],
+        variables.iter().map(|var| var.to_string()),
+        arrays
+            .iter()
+            .flat_map(|(arr, len)| (0..*len).map(move |idx| format!("{arr}[{idx}]"))),
+    );
+
+    let mut csv = csv_row(header);
+    for c in trace {
+        let row = chain!(
+            [c.node.to_string()],
+            variables.iter().map(|var| {
+                c.memory
+                    .get_var(var)
+                    .map(|x| x.to_string())
+                    .unwrap_or_default()
+            }),
+            arrays.iter().flat_map(|(arr, len)| {
+                let values = c.memory.get_arr(arr);
+                (0..*len).map(move |idx| {
+                    values
+                        .and_then(|values| values.get(idx))
+                        .map(|x| x.to_string())
+                        .unwrap_or_default()
+                })
+            }),
+        );
+        csv.push_str(&csv_row(row));
+    }
+    csv
+}
+
+fn csv_row(fields: impl IntoIterator<Item = String>) -> String {
+    let row = fields
+        .into_iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .format(",");
+    format!("{row}\n")
+}
+
+/// Renders a trace as JSON lines, with one configuration per line.
+pub fn trace_to_jsonl<N: Serialize>(trace: &[Configuration<N>]) -> String {
+    trace
+        .iter()
+        .map(|c| serde_json::to_string(c).expect("configurations are serializable") + "\n")
+        .collect()
+}
+
 impl Interpreter {
     pub fn evaluate(
+        steps: u64,
+        memory: InterpreterMemory,
+        pg: &ProgramGraph,
+    ) -> (Vec<Configuration>, TerminationState) {
+        Self::evaluate_with_limits(steps, memory, pg, &InterpreterLimits::default())
+    }
+    /// Like [`Interpreter::evaluate`], but does not run at all if `memory`
+    /// exceeds `limits`, in which case the returned trace is empty. No action
+    /// adds variables or array elements, so the memory of an execution never
+    /// grows beyond its initial size and only needs to be checked once.
+    pub fn evaluate_with_limits(
         mut steps: u64,
         memory: InterpreterMemory,
         pg: &ProgramGraph,
+        limits: &InterpreterLimits,
     ) -> (Vec<Configuration>, TerminationState) {
+        if limits.exceeded_by(&memory) {
+            return (vec![], TerminationState::MemoryLimitExceeded);
+        }
         let mut state = Configuration {
             node: Node::Start,
             memory,
@@ -77,6 +274,294 @@ impl Interpreter {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ReplayError {
+    #[error("the trace is empty")]
+    EmptyTrace,
+    #[error("the trace starts in '{node}' rather than the initial node")]
+    InvalidStart { node: String },
+    #[error("no enabled action leads from step {index} to step {}", index + 1)]
+    Mismatch {
+        index: usize,
+        from: Box<Configuration<String>>,
+        to: Box<Configuration<String>>,
+    },
+}
+
+/// Checks that `trace` is an execution of `cmds`, by verifying that each pair
+/// of consecutive configurations is connected by an edge in the program graph
+/// whose action is enabled and produces exactly the next memory.
+///
+/// Nodes in the trace are identified by their [`Display`](std::fmt::Display)
+/// representation, as produced by the interpreter environment.
+pub fn replay(
+    cmds: &Commands,
+    determinism: Determinism,
+    trace: &[Configuration<String>],
+) -> Result<(), ReplayError> {
+    let pg = ProgramGraph::new(determinism, cmds);
+    let nodes: HashMap<String, Node> = pg.nodes().iter().map(|n| (n.to_string(), *n)).collect();
+
+    let first = trace.first().ok_or(ReplayError::EmptyTrace)?;
+    if first.node != Node::Start.to_string() {
+        return Err(ReplayError::InvalidStart {
+            node: first.node.clone(),
+        });
+    }
+
+    for (index, (from, to)) in trace.iter().tuple_windows().enumerate() {
+        let connected = nodes.get(&from.node).is_some_and(|&node| {
+            pg.outgoing(node).iter().any(|e| {
+                e.to().to_string() == to.node
+                    && e.action().semantics(&from.memory).as_ref() == Ok(&to.memory)
+            })
+        });
+        if !connected {
+            return Err(ReplayError::Mismatch {
+                index,
+                from: Box::new(from.clone()),
+                to: Box::new(to.clone()),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// A loop annotation which does not hold along an execution, see
+/// [`check_annotations`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnnotationViolation {
+    /// The invariant of the loop at `node` does not hold in `memory`
+    Invariant {
+        node: Node,
+        memory: InterpreterMemory,
+    },
+    /// The variant of the loop at `node` is negative when entering its body
+    NegativeVariant { node: Node, value: Int },
+    /// An iteration of the loop at `node` did not decrease its variant
+    NotDecreasing { node: Node, before: Int, after: Int },
+}
+
+/// Checks the loop annotations of `pg` along `trace`. The invariant of a loop
+/// must hold whenever its head is reached, and its variant must be
+/// non-negative when entering the body and strictly smaller once the head is
+/// reached again. Annotations which cannot be evaluated, e.g. because they are
+/// quantified, are skipped.
+pub fn check_annotations(pg: &ProgramGraph, trace: &[Configuration]) -> Vec<AnnotationViolation> {
+    let mut violations = vec![];
+    let mut entered: HashMap<Node, Int> = HashMap::new();
+
+    for (idx, cfg) in trace.iter().enumerate() {
+        let node = cfg.node;
+        let Some(info) = pg.loop_info(node) else {
+            continue;
+        };
+        if let Some(invariant) = &info.invariant {
+            if invariant.semantics(&cfg.memory) == Ok(false) {
+                violations.push(AnnotationViolation::Invariant {
+                    node,
+                    memory: cfg.memory.clone(),
+                });
+            }
+        }
+        let Some(Ok(value)) = info.variant.as_ref().map(|v| v.semantics(&cfg.memory)) else {
+            continue;
+        };
+        if let Some(before) = entered.remove(&node) {
+            if value >= before {
+                violations.push(AnnotationViolation::NotDecreasing {
+                    node,
+                    before,
+                    after: value,
+                });
+            }
+        }
+        let next = trace.get(idx + 1).map(|c| c.node);
+        if info.body_edges.iter().any(|e| Some(e.to()) == next) {
+            if value < 0 {
+                violations.push(AnnotationViolation::NegativeVariant { node, value });
+            }
+            entered.insert(node, value);
+        }
+    }
+
+    violations
+}
+
+/// The outcome of running the tests specified by the `precondition` and
+/// `postcondition` commands of a program, see [`run_tests`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct TestResults {
+    pub passed: usize,
+    /// The executions whose initial memory did not satisfy the preconditions,
+    /// and thus were not run.
+    pub skipped: usize,
+    pub failed: Vec<TestFailure>,
+    pub error: Vec<TestError>,
+}
+
+/// An execution which started in a memory satisfying the preconditions, but
+/// terminated in one violating `postcondition`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TestFailure {
+    pub memory: InterpreterMemory,
+    pub postcondition: BExpr,
+    pub trace: Vec<Configuration>,
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum TestError {
+    #[error("failed to evaluate {condition}: {error}")]
+    Evaluation {
+        memory: Box<InterpreterMemory>,
+        condition: Box<BExpr>,
+        error: InterpreterError,
+    },
+    #[error("the execution did not terminate: {state:?}")]
+    NotTerminated {
+        memory: Box<InterpreterMemory>,
+        state: TerminationState,
+    },
+}
+
+/// A `precondition` or `postcondition` which is not at the top level of the
+/// program, and thus has no clear meaning as a test specification.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+#[error("`{condition}` must be at the top level of the program")]
+pub struct NestedTestCondition {
+    pub condition: Box<Command>,
+}
+
+/// Runs `cmds` from each of `test_memories` for at most `steps` steps, and
+/// checks that the executions starting in a memory satisfying all of the
+/// top-level `precondition`s terminate in one satisfying all of the top-level
+/// `postcondition`s. Executions whose initial memory does not satisfy the
+/// preconditions are skipped, and programs with conditions nested in other
+/// commands are rejected.
+///
+/// If the end of the program graph is too far from the start for any
+/// execution to terminate within `steps`, the executions are not run and all
+/// count as [`TerminationState::Running`].
+pub fn run_tests(
+    cmds: &Commands,
+    determinism: Determinism,
+    test_memories: &[InterpreterMemory],
+    steps: u64,
+) -> Result<TestResults, NestedTestCondition> {
+    if let Some(condition) = cmds.0.iter().find_map(nested_test_condition) {
+        return Err(NestedTestCondition {
+            condition: Box::new(condition.clone()),
+        });
+    }
+
+    let pg = ProgramGraph::new(determinism, cmds);
+    // NOTE: A trace of `steps` configurations takes `steps - 1` edges, and
+    // one more step is needed to observe that the end has no successors
+    let terminable = can_reach_end_within(
+        &pg,
+        Node::Start,
+        usize::try_from(steps.saturating_sub(2)).unwrap_or(usize::MAX),
+    );
+    let preconditions = cmds.0.iter().filter_map(|c| match c {
+        Command::Precondition(b) => Some(b),
+        _ => None,
+    });
+    let postconditions = cmds.0.iter().filter_map(|c| match c {
+        Command::Postcondition(b) => Some(b),
+        _ => None,
+    });
+    let check = |memory: &InterpreterMemory, condition: &BExpr| {
+        condition
+            .semantics(memory)
+            .map_err(|error| TestError::Evaluation {
+                memory: Box::new(memory.clone()),
+                condition: Box::new(condition.clone()),
+                error,
+            })
+    };
+
+    let mut results = TestResults::default();
+    'tests: for memory in test_memories {
+        for pre in preconditions.clone() {
+            match check(memory, pre) {
+                Ok(true) => {}
+                Ok(false) => {
+                    results.skipped += 1;
+                    continue 'tests;
+                }
+                Err(err) => {
+                    results.error.push(err);
+                    continue 'tests;
+                }
+            }
+        }
+
+        if !terminable {
+            results.error.push(TestError::NotTerminated {
+                memory: Box::new(memory.clone()),
+                state: TerminationState::Running,
+            });
+            continue;
+        }
+        let (trace, state) = Interpreter::evaluate(steps, memory.clone(), &pg);
+        if state != TerminationState::Terminated {
+            results.error.push(TestError::NotTerminated {
+                memory: Box::new(memory.clone()),
+                state,
+            });
+            continue;
+        }
+        let last = &trace
+            .last()
+            .expect("terminated traces are not empty")
+            .memory;
+        for post in postconditions.clone() {
+            match check(last, post) {
+                Ok(true) => {}
+                Ok(false) => {
+                    results.failed.push(TestFailure {
+                        memory: memory.clone(),
+                        postcondition: post.clone(),
+                        trace,
+                    });
+                    continue 'tests;
+                }
+                Err(err) => {
+                    results.error.push(err);
+                    continue 'tests;
+                }
+            }
+        }
+        results.passed += 1;
+    }
+
+    Ok(results)
+}
+
+/// The first `precondition` or `postcondition` nested in the body of `cmd`.
+fn nested_test_condition(cmd: &Command) -> Option<&Command> {
+    fn find(cmds: &Commands) -> Option<&Command> {
+        cmds.0.iter().find_map(|c| match c {
+            Command::Precondition(_) | Command::Postcondition(_) => Some(c),
+            _ => nested_test_condition(c),
+        })
+    }
+    match cmd {
+        Command::If(guards) | Command::Loop(guards) | Command::EnrichedLoop(_, _, guards) => {
+            guards.iter().find_map(|g| find(&g.1))
+        }
+        Command::Annotated(_, c, _) | Command::For(_, _, _, c) => find(c),
+        Command::Choice(alternatives) => alternatives.iter().find_map(find),
+        Command::Assignment(_, _)
+        | Command::Skip
+        | Command::Break
+        | Command::Continue
+        | Command::Precondition(_)
+        | Command::Postcondition(_) => None,
+    }
+}
+
 impl Action {
     pub fn semantics(&self, m: &InterpreterMemory) -> Result<InterpreterMemory, InterpreterError> {
         match self {
@@ -206,6 +691,21 @@ impl AExpr {
                         .ok_or(InterpreterError::ArithmeticOverflow)?
                 }
             },
+            AExpr::Cast(x, t) => t.semantic(x.semantics(m)?)?,
+        })
+    }
+}
+
+impl CastTarget {
+    pub fn semantic(&self, x: Int) -> Result<Int, InterpreterError> {
+        Ok(match self {
+            CastTarget::I32 => x as i32 as Int,
+            CastTarget::U32 => x as u32 as Int,
+            // NOTE: Negative values wrap around to values which are too large
+            // to be represented as an `Int`
+            CastTarget::U64 => {
+                Int::try_from(x as u64).map_err(|_| InterpreterError::ArithmeticOverflow)?
+            }
         })
     }
 }
@@ -312,3 +812,263 @@ impl LogicOp {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::{
+        check_annotations, replay, run_tests, sample_memory, trace_to_csv, trace_to_jsonl,
+        AnnotationViolation, Configuration, Interpreter, InterpreterBounds, InterpreterLimits,
+        InterpreterMemory, ReplayError, TerminationState,
+    };
+    use crate::{
+        ast::{Array, Command, Variable},
+        parse::parse_commands,
+        pg::{Determinism, ProgramGraph},
+        sign::MemoryBuilder,
+    };
+
+    #[test]
+    fn memory_constructors() {
+        let cmds = parse_commands("A[0] := B[1]").unwrap();
+
+        let zeroed = InterpreterMemory::zeroed_for(&cmds, 2);
+        assert!(zeroed.variables.is_empty());
+        assert_eq!(zeroed.get_arr(&Array("A".to_string())), Some(&vec![0, 0]));
+        assert_eq!(zeroed.get_arr(&Array("B".to_string())), Some(&vec![0, 0]));
+
+        let built: InterpreterMemory = MemoryBuilder::new()
+            .var("x", 3)
+            .array("A", vec![1, 2])
+            .fill_missing_from(&cmds)
+            .build();
+        assert_eq!(built.get_var(&Variable("x".to_string())), Some(&3));
+        assert_eq!(built.get_arr(&Array("A".to_string())), Some(&vec![1, 2]));
+        assert_eq!(built.get_arr(&Array("B".to_string())), Some(&vec![]));
+
+        let pg = ProgramGraph::new(
+            Determinism::Deterministic,
+            &parse_commands("x := A[y]").unwrap(),
+        );
+        let partial = InterpreterMemory::from_partial(
+            &pg,
+            &[("x".to_string(), 5), ("z".to_string(), 1)]
+                .into_iter()
+                .collect(),
+            &[("B".to_string(), vec![1])].into_iter().collect(),
+            3,
+        );
+        assert_eq!(partial.get_var(&Variable("x".to_string())), Some(&5));
+        assert_eq!(partial.get_var(&Variable("y".to_string())), Some(&0));
+        assert_eq!(partial.get_var(&Variable("z".to_string())), None);
+        assert_eq!(
+            partial.get_arr(&Array("A".to_string())),
+            Some(&vec![0, 0, 0])
+        );
+        assert_eq!(partial.get_arr(&Array("B".to_string())), None);
+    }
+
+    #[test]
+    fn trace_export() {
+        let trace = [
+            Configuration {
+                node: "q▷",
+                memory: MemoryBuilder::new()
+                    .var("x", 1)
+                    .array("A", vec![1, 2])
+                    .build(),
+            },
+            Configuration {
+                node: "q,1",
+                memory: MemoryBuilder::new()
+                    .var("x", -2)
+                    .array("A", vec![3, 4, 5])
+                    .build(),
+            },
+        ];
+
+        assert_eq!(
+            trace_to_csv(&trace),
+            "node,x,A[0],A[1],A[2]\nq▷,1,1,2,\n\"q,1\",-2,3,4,5\n"
+        );
+        assert_eq!(
+            trace_to_jsonl(&trace[..1]),
+            "{\"node\":\"q▷\",\"memory\":{\"variables\":{\"x\":1},\"arrays\":{\"A\":[1,2]}}}\n"
+        );
+    }
+
+    #[test]
+    fn replay_traces() {
+        let cmds = parse_commands("do x < 2 -> x := x + 1 od").unwrap();
+        let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+        let (trace, _) = Interpreter::evaluate(10, InterpreterMemory::zeroed_for(&cmds, 0), &pg);
+        let mut trace = trace
+            .into_iter()
+            .map(|c| c.map_node(|n| n.to_string()))
+            .collect_vec();
+
+        assert_eq!(replay(&cmds, Determinism::Deterministic, &trace), Ok(()));
+
+        let x = Variable("x".to_string());
+        trace[3].memory.variables.insert(x, 5);
+        assert!(matches!(
+            replay(&cmds, Determinism::Deterministic, &trace),
+            Err(ReplayError::Mismatch { index: 2, .. })
+        ));
+        assert_eq!(
+            replay(&cmds, Determinism::Deterministic, &trace[1..]),
+            Err(ReplayError::InvalidStart {
+                node: trace[1].node.clone()
+            })
+        );
+    }
+
+    #[test]
+    fn sample_within_bounds() {
+        use rand::{rngs::SmallRng, SeedableRng};
+
+        let x = Variable("x".to_string());
+        let y = Variable("y".to_string());
+        let bounds = InterpreterBounds {
+            min_values: [(x.clone(), 5), (y.clone(), -3)].into_iter().collect(),
+            max_values: [(x.clone(), 7)].into_iter().collect(),
+        };
+        assert_eq!(bounds.range(&y), -3..=17);
+
+        let mut rng = SmallRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let mem = sample_memory(&bounds, &mut rng);
+            assert_eq!(mem.variables.len(), 2);
+            assert!((5..=7).contains(&mem.variables[&x]));
+            assert!((-3..=17).contains(&mem.variables[&y]));
+        }
+    }
+
+    #[test]
+    fn generate_within_bounds() {
+        use crate::{env::Analysis, ProgramGenerationBuilder};
+
+        let x = Variable("x".to_string());
+        let bounds = InterpreterBounds {
+            min_values: [(x.clone(), 100)].into_iter().collect(),
+            max_values: [(x.clone(), 105)].into_iter().collect(),
+        };
+        for seed in 0..20 {
+            let generated = ProgramGenerationBuilder::new(Analysis::Interpreter)
+                .seed(Some(seed))
+                .interpreter_bounds(bounds.clone())
+                .from_cmds(crate::parse::parse_commands("y := x").unwrap());
+            let input = generated
+                .input
+                .parsed::<crate::env::InterpreterEnv>()
+                .unwrap();
+            assert!((100..=105).contains(&input.assignment.variables[&x]));
+            assert!((-10..=10).contains(&input.assignment.variables[&Variable("y".to_string())]));
+        }
+    }
+
+    #[test]
+    fn memory_limits() {
+        let cmds = parse_commands("x := 1; A[0] := x").unwrap();
+        let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+        let memory = InterpreterMemory::zeroed_for(&cmds, 3);
+
+        let limits = InterpreterLimits {
+            max_array_element_count: Some(2),
+            max_variable_count: None,
+        };
+        let (trace, state) = Interpreter::evaluate_with_limits(10, memory.clone(), &pg, &limits);
+        assert!(trace.is_empty());
+        assert_eq!(state, TerminationState::MemoryLimitExceeded);
+
+        let limits = InterpreterLimits {
+            max_array_element_count: Some(3),
+            max_variable_count: Some(1),
+        };
+        let (trace, state) = Interpreter::evaluate_with_limits(10, memory, &pg, &limits);
+        assert_eq!(trace.len(), 3);
+        assert_eq!(state, TerminationState::Terminated);
+    }
+
+    #[test]
+    fn loop_annotations() {
+        let violations = |src: &str| {
+            let cmds = parse_commands(src).unwrap();
+            let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+            let memory = MemoryBuilder::new().var("x", 3).build();
+            let (trace, _) = Interpreter::evaluate(100, memory, &pg);
+            check_annotations(&pg, &trace)
+        };
+
+        assert_eq!(
+            violations("{ true } do [inv: x >= 0; var: x] x > 0 -> x := x - 1 od { x = 0 }"),
+            []
+        );
+        let increasing =
+            violations("{ true } do [inv: x >= 0; var: 5 - x] x > 0 -> x := x - 1 od { x = 0 }");
+        assert_eq!(
+            increasing
+                .iter()
+                .map(|v| match v {
+                    AnnotationViolation::NotDecreasing { before, after, .. } => (*before, *after),
+                    _ => panic!("unexpected violation {v:?}"),
+                })
+                .collect::<Vec<_>>(),
+            [(2, 3), (3, 4), (4, 5)]
+        );
+        assert!(matches!(
+            violations("{ true } do [inv: x > 0; var: x - 2] x > 0 -> x := x - 1 od { x = 0 }")[..],
+            [
+                AnnotationViolation::NegativeVariant { value: -1, .. },
+                AnnotationViolation::Invariant { .. }
+            ]
+        ));
+    }
+
+    #[test]
+    fn pre_and_postconditions() {
+        let cmds = parse_commands(
+            "precondition x >= 0; y := 0; do x > 0 -> x := x - 1; y := y + 2 od; postcondition y = 4",
+        )
+        .unwrap();
+        let memory = |x| {
+            InterpreterMemory::from_partial(
+                &ProgramGraph::new(Determinism::Deterministic, &cmds),
+                &[("x".to_string(), x)].into_iter().collect(),
+                &Default::default(),
+                0,
+            )
+        };
+
+        let results = run_tests(
+            &cmds,
+            Determinism::Deterministic,
+            &[memory(2), memory(-1), memory(3)],
+            100,
+        )
+        .unwrap();
+        assert_eq!(results.passed, 1);
+        assert_eq!(results.skipped, 1);
+        assert!(results.error.is_empty());
+        assert_eq!(results.failed.len(), 1);
+        assert_eq!(results.failed[0].memory, memory(3));
+        assert_eq!(results.failed[0].postcondition.to_string(), "(y = 4)");
+
+        let results = run_tests(&cmds, Determinism::Deterministic, &[memory(1000)], 10).unwrap();
+        assert!(matches!(
+            results.error[..],
+            [super::TestError::NotTerminated {
+                state: TerminationState::Running,
+                ..
+            }]
+        ));
+
+        let nested = parse_commands("if x > 0 -> postcondition x > 0 [] true -> skip fi").unwrap();
+        assert!(matches!(
+            run_tests(&nested, Determinism::NonDeterministic, &[memory(1)], 10),
+            Err(super::NestedTestCondition { condition })
+                if matches!(*condition, Command::Postcondition(_))
+        ));
+    }
+}
@@ -28,11 +28,20 @@
 //! Similarly, the inputs of [`Environment`] implementations must too implement
 //! [`Generate`].
 
-use std::{borrow::Cow, time::Duration};
+use std::{borrow::Cow, panic::AssertUnwindSafe, sync::mpsc, time::Duration};
 
+use ast::Command;
 use driver::Driver;
-use env::{Analysis, Environment, Input, ValidationResult};
+use env::{
+    interpreter::InterpreterInput,
+    security::{LatticeTemplate, SecurityAnalysisInput},
+    Analysis, AnalysisOutput, EnvError, Environment, Input, InterpreterEnv, SecurityEnv,
+    ValidationResult,
+};
+use interpreter::InterpreterBounds;
 pub use miette;
+use parse::ParseError;
+use progress::{ProgressCallback, ProgressEvent};
 use rand::prelude::*;
 use tracing::debug;
 
@@ -41,20 +50,32 @@ use crate::ast::Commands;
 pub mod analysis;
 pub mod ast;
 pub mod config;
+pub mod desugar;
+pub mod dot;
 pub mod driver;
 pub mod egg;
 pub mod env;
 pub mod fmt;
+pub mod fuzz;
 mod gcl;
 pub mod generation;
 pub mod interpreter;
+pub mod maude;
 pub mod parse;
+pub mod pdg;
 pub mod pg;
+pub mod prism;
+pub mod progress;
 pub mod pv;
 pub mod security;
+pub mod shrink;
 pub mod sign;
+#[cfg(test)]
+mod tests;
+pub mod uninit;
+pub mod wp;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ProgramGenerationBuilder {
     analysis: Analysis,
     fuel: Option<u32>,
@@ -62,6 +83,9 @@ pub struct ProgramGenerationBuilder {
     no_loop: bool,
     no_division: bool,
     generate_annotated: bool,
+    lattice_template: Option<LatticeTemplate>,
+    interpreter_bounds: InterpreterBounds,
+    timeout: Option<Duration>,
 }
 
 impl Commands {
@@ -79,6 +103,9 @@ impl ProgramGenerationBuilder {
             no_loop: Default::default(),
             no_division: Default::default(),
             generate_annotated: Default::default(),
+            lattice_template: Default::default(),
+            interpreter_bounds: Default::default(),
+            timeout: Default::default(),
         }
     }
 
@@ -104,6 +131,27 @@ impl ProgramGenerationBuilder {
             ..self
         }
     }
+    /// The shape of the lattice in generated security analysis inputs, which
+    /// is chosen at random if not set.
+    pub fn lattice_template(self, lattice_template: Option<LatticeTemplate>) -> Self {
+        ProgramGenerationBuilder {
+            lattice_template,
+            ..self
+        }
+    }
+    /// The ranges of the initial values of variables in generated interpreter
+    /// inputs.
+    pub fn interpreter_bounds(self, interpreter_bounds: InterpreterBounds) -> Self {
+        ProgramGenerationBuilder {
+            interpreter_bounds,
+            ..self
+        }
+    }
+    /// The time the generated program may run for, which is
+    /// [`DEFAULT_TIMEOUT`] if not set.
+    pub fn timeout(self, timeout: Option<Duration>) -> Self {
+        ProgramGenerationBuilder { timeout, ..self }
+    }
     fn internal_build(self, cmds: Option<Commands>, input: Option<Input>) -> GeneratedProgram {
         let seed = match self.seed {
             Some(seed) => seed,
@@ -128,13 +176,26 @@ impl ProgramGenerationBuilder {
                 }
             }
         };
-        let input = input.unwrap_or_else(|| self.analysis.gen_input(&cmds, &mut rng));
+        let input = input.unwrap_or_else(|| match (self.analysis, self.lattice_template) {
+            (Analysis::Security, Some(template)) => Input::from_concrete::<SecurityEnv>(
+                &SecurityAnalysisInput::gen_with_template(&mut cmds.clone(), &mut rng, template),
+            ),
+            (Analysis::Interpreter, _) => {
+                Input::from_concrete::<InterpreterEnv>(&InterpreterInput::gen_with_bounds(
+                    &mut cmds.clone(),
+                    &mut rng,
+                    &self.interpreter_bounds,
+                ))
+            }
+            _ => self.analysis.gen_input(&cmds, &mut rng),
+        });
 
         GeneratedProgram {
             cmds,
             input,
             fuel,
             seed,
+            timeout: self.timeout.unwrap_or(DEFAULT_TIMEOUT),
         }
     }
     pub fn from_cmds(self, cmds: Commands) -> GeneratedProgram {
@@ -148,12 +209,40 @@ impl ProgramGenerationBuilder {
     }
 }
 
+/// The fixed corpus of programs used by the benchmarks, named by what they
+/// exercise. The generated programs always come from the same seeds, such that
+/// the numbers are comparable between runs.
+pub fn bench_programs() -> Vec<(&'static str, Commands)> {
+    let generated = (0..200)
+        .flat_map(|seed| {
+            ProgramGenerationBuilder::new(Analysis::Graph)
+                .seed(Some(seed))
+                .build()
+                .cmds
+                .0
+        })
+        .collect();
+    vec![
+        ("generated", Commands(generated)),
+        (
+            "counting loop",
+            parse::parse_commands("i := 0; do i < 1000 -> i := i + 1 od")
+                .expect("the counting loop parses"),
+        ),
+    ]
+}
+
+/// The time a program may run for in [`GeneratedProgram::run_analysis`],
+/// unless [another is given](ProgramGenerationBuilder::timeout).
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug)]
 pub struct GeneratedProgram {
     pub cmds: Commands,
     pub input: Input,
     pub fuel: u32,
     pub seed: u64,
+    pub timeout: Duration,
 }
 
 impl GeneratedProgram {
@@ -161,6 +250,41 @@ impl GeneratedProgram {
         self,
         env: &E,
         driver: &Driver,
+    ) -> AnalysisSummary<E> {
+        self.run_analysis_with_progress(env, driver, &()).await
+    }
+    /// Like [`GeneratedProgram::run_analysis`], but also reports the start and
+    /// the outcome of the run to `progress`.
+    pub async fn run_analysis_with_progress<E: Environment>(
+        self,
+        env: &E,
+        driver: &Driver,
+        progress: &impl ProgressCallback,
+    ) -> AnalysisSummary<E> {
+        let seed = self.seed;
+        progress::emit(
+            progress,
+            ProgressEvent::SampleStarted {
+                analysis: E::ANALYSIS,
+                seed,
+            },
+        );
+        let summary = self.exec_and_validate(env, driver).await;
+        progress::emit(
+            progress,
+            ProgressEvent::SampleFinished {
+                analysis: E::ANALYSIS,
+                seed,
+                time: summary.time,
+                verdict: progress::verdict(&summary.result),
+            },
+        );
+        summary
+    }
+    async fn exec_and_validate<E: Environment>(
+        self,
+        env: &E,
+        driver: &Driver,
     ) -> AnalysisSummary<E> {
         debug!(name = E::ANALYSIS.to_string(), "running analysis");
 
@@ -169,13 +293,12 @@ impl GeneratedProgram {
             input,
             fuel,
             seed,
+            timeout,
         } = self;
 
         let input = input.parsed::<E>().unwrap();
 
-        let timeout_duration = Duration::from_secs(10);
-        let exec_result =
-            tokio::time::timeout(timeout_duration, driver.exec::<E>(&cmds, &input)).await;
+        let exec_result = tokio::time::timeout(timeout, driver.exec::<E>(&cmds, &input)).await;
         match exec_result {
             Err(_) => AnalysisSummary {
                 fuel,
@@ -183,7 +306,7 @@ impl GeneratedProgram {
                 cmds,
                 input,
                 output: None,
-                time: timeout_duration,
+                time: timeout,
                 stdout: String::new(),
                 stderr: String::new(),
                 result: Ok(ValidationResult::TimeOut),
@@ -236,6 +359,63 @@ impl GeneratedProgram {
                     stderr: truncated_from_utf8(&output.stderr),
                     result: Err(driver::ExecError::CommandFailed(output, time).into()),
                 },
+                driver::ExecError::UnsupportedAnalysis {
+                    analysis,
+                    output,
+                    time,
+                } => AnalysisSummary {
+                    fuel,
+                    seed,
+                    cmds,
+                    input,
+                    output: None,
+                    time,
+                    stdout: truncated_from_utf8(&output.stdout),
+                    stderr: truncated_from_utf8(&output.stderr),
+                    result: Err(driver::ExecError::UnsupportedAnalysis {
+                        analysis,
+                        output,
+                        time,
+                    }
+                    .into()),
+                },
+                driver::ExecError::CpuLimitExceeded { output, time } => AnalysisSummary {
+                    fuel,
+                    seed,
+                    cmds,
+                    input,
+                    output: None,
+                    time,
+                    stdout: truncated_from_utf8(&output.stdout),
+                    stderr: truncated_from_utf8(&output.stderr),
+                    result: Err(driver::ExecError::CpuLimitExceeded { output, time }.into()),
+                },
+                driver::ExecError::MemoryLimitExceeded { output, time } => AnalysisSummary {
+                    fuel,
+                    seed,
+                    cmds,
+                    input,
+                    output: None,
+                    time,
+                    stdout: truncated_from_utf8(&output.stdout),
+                    stderr: truncated_from_utf8(&output.stderr),
+                    result: Err(driver::ExecError::MemoryLimitExceeded { output, time }.into()),
+                },
+                driver::ExecError::InvalidCapabilities {
+                    source,
+                    output,
+                    time,
+                } => AnalysisSummary {
+                    fuel,
+                    seed,
+                    cmds,
+                    input,
+                    output: None,
+                    time,
+                    stdout: truncated_from_utf8(&output.stdout),
+                    stderr: truncated_from_utf8(&output.stderr),
+                    result: Err(source.into()),
+                },
                 driver::ExecError::Parse {
                     inner,
                     run_output,
@@ -280,3 +460,105 @@ pub struct AnalysisSummary<E: Environment> {
     pub stderr: String,
     pub result: color_eyre::Result<ValidationResult>,
 }
+
+#[derive(Debug, thiserror::Error)]
+pub enum AnalyseError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("`{construct}` is not supported by {analysis}")]
+    Unsupported {
+        analysis: Analysis,
+        construct: &'static str,
+    },
+    #[error(transparent)]
+    Env(#[from] EnvError),
+    /// The analysis did not finish within the deadline given to [`analyse`].
+    ///
+    /// NOTE: The analysis cannot be interrupted, so its thread keeps running
+    /// and holding its memory until it finishes on its own.
+    #[error("the analysis did not finish within {0:?}")]
+    TimedOut(Duration),
+    #[error("the analysis panicked: {0}")]
+    Panicked(String),
+}
+
+/// Parses `program`, runs `analysis` on it with the JSON `input` and returns
+/// the output, without panicking on any program or input.
+///
+/// If a `deadline` is given the analysis runs on a separate thread, and
+/// [`AnalyseError::TimedOut`] is returned if it takes longer than that.
+///
+/// NOTE: The analyses have no way of being cancelled, so a thread which misses
+/// its deadline is detached rather than stopped. It keeps using a core and its
+/// memory until the analysis finishes, which may take far longer than the
+/// deadline, so long-lived callers analysing untrusted programs should run
+/// them in a separate process which can be killed instead.
+pub fn analyse(
+    program: &str,
+    analysis: Analysis,
+    input: &str,
+    deadline: Option<Duration>,
+) -> Result<AnalysisOutput, AnalyseError> {
+    let cmds = parse::parse_commands(program)?;
+    if let Some(construct) = unsupported_construct(analysis, &cmds.0) {
+        return Err(AnalyseError::Unsupported {
+            analysis,
+            construct,
+        });
+    }
+    let input = analysis.input_from_str(input)?;
+
+    let run = move || {
+        std::panic::catch_unwind(AssertUnwindSafe(|| {
+            analysis
+                .run(&cmds, input)
+                .and_then(env::Output::into_analysis_output)
+        }))
+        .map_err(|payload| {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            AnalyseError::Panicked(message)
+        })
+    };
+    let result = match deadline {
+        None => run(),
+        Some(deadline) => {
+            let (tx, rx) = mpsc::channel();
+            std::thread::spawn(move || {
+                let _ = tx.send(run());
+            });
+            rx.recv_timeout(deadline)
+                .map_err(|_| AnalyseError::TimedOut(deadline))?
+        }
+    };
+    Ok(result??)
+}
+
+/// The first construct in `cmds` which `analysis` cannot handle, if any.
+fn unsupported_construct(analysis: Analysis, cmds: &[Command]) -> Option<&'static str> {
+    // NOTE: Only parsing is defined for `break` and `continue`, as they have no
+    // program graph yet
+    if analysis == Analysis::Parse {
+        return None;
+    }
+    cmds.iter().find_map(|cmd| match cmd {
+        Command::Assignment(_, _)
+        | Command::Skip
+        | Command::Precondition(_)
+        | Command::Postcondition(_) => None,
+        Command::Break => Some("break"),
+        Command::Continue => Some("continue"),
+        Command::If(guards) | Command::Loop(guards) | Command::EnrichedLoop(_, _, guards) => guards
+            .iter()
+            .find_map(|guard| unsupported_construct(analysis, &guard.1 .0)),
+        Command::Annotated(_, body, _) | Command::For(_, _, _, body) => {
+            unsupported_construct(analysis, &body.0)
+        }
+        Command::Choice(alternatives) => alternatives
+            .iter()
+            .find_map(|c| unsupported_construct(analysis, &c.0)),
+    })
+}
@@ -1,6 +1,15 @@
+use std::{path::PathBuf, str::FromStr};
+
 use clap::Parser;
 
-use checkr::{env::Analysis, parse};
+use checkr::{
+    analyse,
+    env::{Analysis, AnalysisOutput, InterpreterEnv},
+    interpreter::{replay, trace_to_csv, trace_to_jsonl},
+    parse,
+    pg::Determinism,
+};
+use color_eyre::eyre::{bail, eyre};
 
 #[derive(Debug, Parser)]
 #[command(version)]
@@ -11,9 +20,39 @@ enum Cli {
         analysis: Analysis,
         src: String,
         input: String,
+        /// Export the trace of the interpreter as `csv:<path>` or `jsonl:<path>`
+        #[arg(long)]
+        export: Option<Export>,
+    },
+    /// Replay the trace of an interpreter output to check that it is valid
+    Replay {
+        src: String,
+        /// Path to the JSON output of the interpreter
+        output: PathBuf,
+        /// One of `det`, `nondet`, `true` or `false`
+        #[arg(long, default_value_t = Determinism::Deterministic)]
+        determinism: Determinism,
     },
 }
 
+#[derive(Debug, Clone)]
+enum Export {
+    Csv(PathBuf),
+    JsonLines(PathBuf),
+}
+
+impl FromStr for Export {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("csv", path)) => Ok(Export::Csv(path.into())),
+            Some(("jsonl", path)) => Ok(Export::JsonLines(path.into())),
+            _ => Err(eyre!("expected `csv:<path>` or `jsonl:<path>`, got `{s}`")),
+        }
+    }
+}
+
 fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
 
@@ -27,11 +66,40 @@ fn main() -> color_eyre::Result<()> {
             analysis,
             src,
             input,
+            export,
+        } => {
+            let output = analyse(&src, analysis, &input, None)?;
+
+            println!("{}", output.to_output());
+
+            if let Some(export) = export {
+                let AnalysisOutput::Interpreter(output) = output else {
+                    bail!("only the trace of the interpreter can be exported");
+                };
+                let trace = output.execution_sequence();
+                match export {
+                    Export::Csv(path) => std::fs::write(path, trace_to_csv(trace))?,
+                    Export::JsonLines(path) => std::fs::write(path, trace_to_jsonl(trace))?,
+                }
+            }
+
+            Ok(())
+        }
+        Cli::Replay {
+            src,
+            output,
+            determinism,
         } => {
             let cmds = parse::parse_commands(&src)?;
-            let output = analysis.run(&cmds, analysis.input_from_str(&input)?)?;
+            let output = Analysis::Interpreter
+                .output_from_slice(&std::fs::read(output)?)?
+                .parsed::<InterpreterEnv>()?;
+            replay(&cmds, determinism, output.execution_sequence())?;
 
-            println!("{output}");
+            println!(
+                "The trace of {} steps is valid",
+                output.execution_sequence().len()
+            );
 
             Ok(())
         }
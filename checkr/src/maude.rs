@@ -0,0 +1,221 @@
+//! Export of program graphs as Maude rewrite theories.
+//!
+//! A program graph is translated into a system module where a configuration
+//! `config(node, store)` pairs a node with a store mapping variable names to
+//! their values, e.g. `('x |-> 1) ('y |-> 2)`. Every edge becomes a rewrite
+//! rule, which is conditional if the edge is labeled with a condition. The
+//! reachable states can then be explored with Maude's `search` command:
+//!
+//! ```text
+//! search config(qStart, ('x |-> 3) ('y |-> 0)) =>* config(qFinal, S:Store) .
+//! ```
+
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+
+use crate::{
+    ast::{AExpr, AOp, BExpr, Function, LogicOp, RelOp, Target, Variable},
+    pg::{Action, Node, NodeStyle, ProgramGraph},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum MaudeError {
+    #[error("arrays are not supported in the Maude export")]
+    Array,
+    #[error("{0} is not supported in the Maude export")]
+    Unsupported(String),
+}
+
+/// Translates `pg` into a Maude module named `module_name`.
+pub fn pg_to_maude(pg: &ProgramGraph, module_name: &str) -> Result<String, MaudeError> {
+    // Number the variables in alphabetical order, such that the output is
+    // stable
+    let vars = pg
+        .fv()
+        .into_iter()
+        .map(|t| match t {
+            Target::Variable(v) => Ok(v),
+            Target::Array(_, ()) => Err(MaudeError::Array),
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .sorted()
+        .enumerate()
+        .map(|(idx, v)| (v, idx))
+        .collect();
+    let ctx = Context { vars };
+
+    let rules = pg
+        .edges()
+        .iter()
+        .enumerate()
+        .map(|(idx, e)| {
+            let lhs = format!("config({}, {})", node(e.from()), ctx.store(None));
+            Ok(match e.action() {
+                Action::Assignment(Target::Variable(x), a) => {
+                    let a = ctx.aexpr(a)?;
+                    let rhs = format!("config({}, {})", node(e.to()), ctx.store(Some((x, a))));
+                    format!("  rl [e{idx}] : {lhs} => {rhs} .")
+                }
+                Action::Assignment(Target::Array(_, _), _) => return Err(MaudeError::Array),
+                Action::Skip | Action::Condition(BExpr::Bool(true)) => {
+                    let rhs = format!("config({}, {})", node(e.to()), ctx.store(None));
+                    format!("  rl [e{idx}] : {lhs} => {rhs} .")
+                }
+                Action::Condition(b) => {
+                    let rhs = format!("config({}, {})", node(e.to()), ctx.store(None));
+                    format!(
+                        "  crl [e{idx}] : {lhs} => {rhs} if {} = true .",
+                        ctx.bexpr(b)?
+                    )
+                }
+            })
+        })
+        .collect::<Result<Vec<_>, MaudeError>>()?;
+
+    let mut lines = vec![
+        format!("mod {module_name} is"),
+        "  protecting INT .".to_string(),
+        "  protecting QID .".to_string(),
+        "  sorts Node Binding Store Config .".to_string(),
+        "  subsort Binding < Store .".to_string(),
+        format!(
+            "  ops {} : -> Node [ctor] .",
+            pg.nodes().iter().sorted().map(|&n| node(n)).format(" ")
+        ),
+        "  op _|->_ : Qid Int -> Binding [ctor] .".to_string(),
+        "  op none : -> Store [ctor] .".to_string(),
+        "  op __ : Store Store -> Store [ctor assoc comm id: none] .".to_string(),
+        "  op config : Node Store -> Config [ctor] .".to_string(),
+    ];
+    if !ctx.vars.is_empty() {
+        lines.push(format!(
+            "  vars {} : Int .",
+            ctx.vars.values().map(|idx| format!("V{idx}")).format(" ")
+        ));
+    }
+    lines.extend(rules);
+    lines.push("endm".to_string());
+
+    Ok(lines.join("\n"))
+}
+
+fn node(n: Node) -> String {
+    n.display_with(&NodeStyle::Ascii).to_string()
+}
+
+struct Context {
+    vars: BTreeMap<Variable, usize>,
+}
+
+impl Context {
+    /// The store where every variable is bound to its Maude variable, except
+    /// for `update` which is bound to the given expression.
+    fn store(&self, update: Option<(&Variable, String)>) -> String {
+        if self.vars.is_empty() {
+            return "none".to_string();
+        }
+        self.vars
+            .iter()
+            .map(|(v, idx)| match &update {
+                Some((x, a)) if *x == v => format!("('{} |-> {a})", v.0),
+                _ => format!("('{} |-> V{idx})", v.0),
+            })
+            .join(" ")
+    }
+    fn aexpr(&self, a: &AExpr) -> Result<String, MaudeError> {
+        Ok(match a {
+            AExpr::Number(n) => n.to_string(),
+            AExpr::Reference(Target::Variable(v)) => format!("V{}", self.vars[v]),
+            AExpr::Reference(Target::Array(_, _)) => return Err(MaudeError::Array),
+            AExpr::Binary(l, op, r) => {
+                let op = match op {
+                    AOp::Plus => "+",
+                    AOp::Minus => "-",
+                    AOp::Times => "*",
+                    AOp::Divide => "quo",
+                    AOp::Pow => "^",
+                };
+                format!("({} {op} {})", self.aexpr(l)?, self.aexpr(r)?)
+            }
+            AExpr::Minus(x) => format!("(- {})", self.aexpr(x)?),
+            AExpr::Function(Function::Division(l, r)) => {
+                format!("({} quo {})", self.aexpr(l)?, self.aexpr(r)?)
+            }
+            AExpr::Function(Function::Min(l, r)) => {
+                format!("min({}, {})", self.aexpr(l)?, self.aexpr(r)?)
+            }
+            AExpr::Function(Function::Max(l, r)) => {
+                format!("max({}, {})", self.aexpr(l)?, self.aexpr(r)?)
+            }
+            AExpr::Function(f) => return Err(MaudeError::Unsupported(f.to_string())),
+            AExpr::Cast(_, _) => return Err(MaudeError::Unsupported(a.to_string())),
+        })
+    }
+    fn bexpr(&self, b: &BExpr) -> Result<String, MaudeError> {
+        Ok(match b {
+            BExpr::Bool(b) => b.to_string(),
+            BExpr::Rel(l, op, r) => {
+                let op = match op {
+                    RelOp::Eq => "==",
+                    RelOp::Ne => "=/=",
+                    RelOp::Gt => ">",
+                    RelOp::Ge => ">=",
+                    RelOp::Lt => "<",
+                    RelOp::Le => "<=",
+                };
+                format!("({} {op} {})", self.aexpr(l)?, self.aexpr(r)?)
+            }
+            BExpr::Logic(l, op, r) => {
+                let op = match op {
+                    LogicOp::And | LogicOp::Land => "and",
+                    LogicOp::Or | LogicOp::Lor => "or",
+                    LogicOp::Implies => "implies",
+                };
+                format!("({} {op} {})", self.bexpr(l)?, self.bexpr(r)?)
+            }
+            BExpr::Not(x) => format!("(not {})", self.bexpr(x)?),
+            BExpr::Quantified(_, _, _) => return Err(MaudeError::Unsupported(b.to_string())),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pg_to_maude, MaudeError};
+    use crate::{
+        parse::parse_commands,
+        pg::{Determinism, ProgramGraph},
+    };
+
+    #[test]
+    fn maude_module() {
+        let cmds = parse_commands("do x > 0 -> x := x - 1; y := y + 2 od").unwrap();
+        let pg = ProgramGraph::new(Determinism::NonDeterministic, &cmds);
+
+        assert_eq!(
+            pg_to_maude(&pg, "LOOP").unwrap(),
+            r#"mod LOOP is
+  protecting INT .
+  protecting QID .
+  sorts Node Binding Store Config .
+  subsort Binding < Store .
+  ops qStart q1 q2 qFinal : -> Node [ctor] .
+  op _|->_ : Qid Int -> Binding [ctor] .
+  op none : -> Store [ctor] .
+  op __ : Store Store -> Store [ctor assoc comm id: none] .
+  op config : Node Store -> Config [ctor] .
+  vars V0 V1 : Int .
+  rl [e0] : config(q1, ('x |-> V0) ('y |-> V1)) => config(q2, ('x |-> (V0 - 1)) ('y |-> V1)) .
+  rl [e1] : config(q2, ('x |-> V0) ('y |-> V1)) => config(qStart, ('x |-> V0) ('y |-> (V1 + 2))) .
+  crl [e2] : config(qStart, ('x |-> V0) ('y |-> V1)) => config(q1, ('x |-> V0) ('y |-> V1)) if (V0 > 0) = true .
+  crl [e3] : config(qStart, ('x |-> V0) ('y |-> V1)) => config(qFinal, ('x |-> V0) ('y |-> V1)) if (not (V0 > 0)) = true .
+endm"#
+        );
+
+        let cmds = parse_commands("A[0] := 1").unwrap();
+        let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+        assert_eq!(pg_to_maude(&pg, "ARR"), Err(MaudeError::Array));
+    }
+}
@@ -83,37 +83,80 @@ pub fn parse_predicate(src: &str) -> Result<Predicate, ParseError> {
     PARSER.parse(src).map_err(|e| ParseError::new(src, e))
 }
 
+/// An error from parsing GCL source. The `line` and `column` of the error are
+/// both 1-based.
 #[derive(Debug, Error, Diagnostic, Clone)]
 pub enum ParseError {
-    #[error("Invalid Token")]
+    #[error("error at line {line}, col {column}: invalid token")]
     #[diagnostic()]
     InvalidToken {
         #[source_code]
         src: String,
         #[label("This token is not valid in this context")]
         err_span: SourceSpan,
+        line: usize,
+        column: usize,
     },
-    #[error("Unrecognized Token")]
-    #[diagnostic(help("Expected tokens here are: {expected}{}", if let Some(hint) = hint { format!("\n{hint}") } else { "".to_string() }))]
+    #[error(
+        "error at line {line}, col {column}: expected {}, found '{token}'",
+        expected_list(expected)
+    )]
+    #[diagnostic(help("Expected tokens here are: {}{}", expected.join(", "), if let Some(hint) = hint { format!("\n{hint}") } else { "".to_string() }))]
     UnrecognizedToken {
         #[source_code]
         src: String,
         #[label = "The token \"{token}\" is unrecognized in this context."]
         err_span: SourceSpan,
+        line: usize,
+        column: usize,
         token: String,
-        expected: String,
-        hint: Option<String>,
+        expected: Vec<String>,
+        hint: Option<Box<str>>,
     },
-    #[error("Unrecognized EOF")]
-    #[diagnostic(help("Expected tokens in this context are:\n{expected}"))]
+    #[error(
+        "error at line {line}, col {column}: expected {}, found end of input",
+        expected_list(expected)
+    )]
+    #[diagnostic(help("Expected tokens in this context are:\n{}", expected.join(", ")))]
     UnrecognizedEOF {
         #[source_code]
         src: String,
         #[label = "The document ends too early. Are you missing a token?"]
         err_span: SourceSpan,
-        expected: String,
+        line: usize,
+        column: usize,
+        expected: Vec<String>,
     },
 }
+
+/// Formats the expected tokens as `';', 'od' or 'fi'`. When no token is
+/// expected, only the end of the input is.
+fn expected_list(expected: &[String]) -> String {
+    let quoted = expected
+        .iter()
+        .map(
+            |t| match t.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+                Some(t) => format!("'{t}'"),
+                None => t.clone(),
+            },
+        )
+        .collect::<Vec<_>>();
+    match quoted.split_last() {
+        None => "end of input".to_string(),
+        Some((last, [])) => last.clone(),
+        Some((last, init)) => format!("{} or {last}", init.join(", ")),
+    }
+}
+
+/// The 1-based line and column of the byte `offset` in `src`.
+fn line_column(src: &str, offset: usize) -> (usize, usize) {
+    let before = &src[..offset.min(src.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
 // impl ParseError {
 //     pub fn span(&self) -> Span {
 //         match self {
@@ -135,23 +178,34 @@ impl ParseError {
         let prep_src = || format!("{src}\n");
 
         match e {
-            lalrpop_util::ParseError::InvalidToken { location } => ParseError::InvalidToken {
-                src: prep_src(),
-                err_span: (location, 0).into(),
-            },
+            lalrpop_util::ParseError::InvalidToken { location } => {
+                let (line, column) = line_column(src, location);
+                ParseError::InvalidToken {
+                    src: prep_src(),
+                    err_span: (location, 0).into(),
+                    line,
+                    column,
+                }
+            }
             lalrpop_util::ParseError::UnrecognizedEOF { location, expected } => {
+                let (line, column) = line_column(src, location);
                 ParseError::UnrecognizedEOF {
                     src: prep_src(),
                     err_span: (location, 0).into(),
-                    expected: expected.join(", "),
+                    line,
+                    column,
+                    expected,
                 }
             }
             lalrpop_util::ParseError::UnrecognizedToken { token, expected } => {
+                let (line, column) = line_column(src, token.0);
                 ParseError::UnrecognizedToken {
                     src: prep_src(),
                     err_span: (token.0, token.2 - token.0).into(),
+                    line,
+                    column,
                     token: token.1.to_string(),
-                    expected: expected.join(", "),
+                    expected,
                     hint: None,
                 }
             }
@@ -160,3 +214,32 @@ impl ParseError {
         }
     }
 }
+
+#[test]
+fn error_positions() {
+    let err = parse_commands("x := 1;\nif x > 0 -> skip\nfi fi").unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::UnrecognizedToken {
+            line: 3,
+            column: 4,
+            ..
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "error at line 3, col 4: expected end of input, found 'fi'"
+    );
+
+    let err = parse_commands("x := 1;\n  y :=").unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("error at line 2, col 7: expected '(', '-', 'count'"));
+    assert!(err.to_string().ends_with(", found end of input"));
+
+    let err = parse_commands("if x > 0 -> skip").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "error at line 1, col 17: expected '[]', 'fi' or 'od', found end of input"
+    );
+}
@@ -0,0 +1,178 @@
+//! Program dependence graphs, combining the data and control dependences of
+//! the nodes of a [`ProgramGraph`].
+
+use std::collections::{HashMap, HashSet};
+
+use petgraph::{algo::dominators, visit::Reversed};
+
+use crate::{
+    analysis::{mono_analysis, Direction, FiFo, MonotoneFramework},
+    ast::Target,
+    pg::{Action, Edge, Node, ProgramGraph},
+};
+
+/// The dependences of the nodes of a program graph, where a node stands for
+/// the actions on its outgoing edges.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProgramDependenceGraph {
+    /// The nodes whose assignments might define a value used by each node
+    pub data_deps: HashMap<Node, HashSet<Node>>,
+    /// The branching nodes which decide whether each node is executed
+    pub control_deps: HashMap<Node, HashSet<Node>>,
+}
+
+pub fn build_pdg(pg: &ProgramGraph) -> ProgramDependenceGraph {
+    ProgramDependenceGraph {
+        data_deps: data_deps(pg),
+        control_deps: control_deps(pg),
+    }
+}
+
+/// The reaching definitions analysis, where a definition is a target together
+/// with the node whose outgoing edge assigns it.
+struct ReachingDefinitions;
+
+impl MonotoneFramework for ReachingDefinitions {
+    type Domain = HashSet<(Target, Node)>;
+
+    fn semantic(&self, _pg: &ProgramGraph, e: &Edge, prev: &Self::Domain) -> Self::Domain {
+        match e.action() {
+            Action::Assignment(x, _) => {
+                let x = x.clone().unit();
+                let mut next: Self::Domain = match &x {
+                    // Assigning an array element does not overwrite the rest
+                    // of the array
+                    Target::Array(_, ()) => prev.clone(),
+                    Target::Variable(_) => prev.iter().filter(|(t, _)| *t != x).cloned().collect(),
+                };
+                next.insert((x, e.from()));
+                next
+            }
+            Action::Skip | Action::Condition(_) => prev.clone(),
+        }
+    }
+
+    fn direction() -> Direction {
+        Direction::Forward
+    }
+
+    fn initial(&self, _pg: &ProgramGraph) -> Self::Domain {
+        Default::default()
+    }
+}
+
+/// The targets whose values are read by the action of `e`.
+pub(crate) fn uses(e: &Edge) -> HashSet<Target> {
+    match e.action() {
+        Action::Assignment(Target::Variable(_), a) => a.fv(),
+        Action::Assignment(Target::Array(_, idx), a) => idx.fv().union(&a.fv()).cloned().collect(),
+        Action::Skip => Default::default(),
+        Action::Condition(b) => b.fv(),
+    }
+}
+
+fn data_deps(pg: &ProgramGraph) -> HashMap<Node, HashSet<Node>> {
+    let reaching = mono_analysis::<_, FiFo>(ReachingDefinitions, pg).facts;
+
+    pg.nodes()
+        .iter()
+        .map(|&n| {
+            let used: HashSet<Target> = pg.outgoing(n).iter().flat_map(uses).collect();
+            let deps = reaching[&n]
+                .iter()
+                .filter(|(t, _)| used.contains(t))
+                .map(|(_, def)| *def)
+                .collect();
+            (n, deps)
+        })
+        .collect()
+}
+
+/// Computes control dependences from the post-dominator tree: a node `n` is
+/// control dependent on `m` if `m` has an edge into a path where `n` is always
+/// executed, while `n` does not post-dominate `m` itself.
+fn control_deps(pg: &ProgramGraph) -> HashMap<Node, HashSet<Node>> {
+    let mut deps: HashMap<Node, HashSet<Node>> =
+        pg.nodes().iter().map(|&n| (n, HashSet::new())).collect();
+
+    let (g, node_mapping, node_mapping_rev) = pg.as_petgraph();
+    let Some(&end) = node_mapping.get(&Node::End) else {
+        return deps;
+    };
+    let post_dominators = dominators::simple_fast(Reversed(&g), end);
+
+    for e in pg.edges() {
+        let from = node_mapping[&e.from()];
+        let ipdom = post_dominators.immediate_dominator(from);
+        let mut runner = Some(node_mapping[&e.to()]);
+        while let Some(n) = runner {
+            if Some(n) == ipdom {
+                break;
+            }
+            deps.get_mut(&node_mapping_rev[&n])
+                .unwrap()
+                .insert(e.from());
+            if n == from {
+                // The edge is a back edge to a loop head, which is control
+                // dependent on itself
+                break;
+            }
+            runner = post_dominators.immediate_dominator(n);
+        }
+    }
+
+    deps
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::build_pdg;
+    use crate::{
+        parse::parse_commands,
+        pg::{Determinism, Node, ProgramGraph},
+    };
+
+    fn nodes<const N: usize>(names: [&str; N]) -> HashSet<Node> {
+        names.into_iter().map(|n| n.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn dependences() {
+        let cmds =
+            parse_commands("x := 1; y := 2; if x > 0 -> z := x + y [] x <= 0 -> z := 0 fi; w := z")
+                .unwrap();
+        let pg = ProgramGraph::new(Determinism::NonDeterministic, &cmds);
+        let pdg = build_pdg(&pg);
+
+        // The branches of the `if` start in q4 and q3, and join in q5
+        assert_eq!(
+            pg.outgoing("q4".parse().unwrap())[0].action().to_string(),
+            "z := (x + y)"
+        );
+        assert_eq!(pdg.data_deps[&"q2".parse().unwrap()], nodes(["qStart"]));
+        assert_eq!(
+            pdg.data_deps[&"q4".parse().unwrap()],
+            nodes(["qStart", "q1"])
+        );
+        assert_eq!(pdg.data_deps[&"q5".parse().unwrap()], nodes(["q3", "q4"]));
+        assert_eq!(pdg.data_deps[&"q3".parse().unwrap()], nodes([]));
+
+        assert_eq!(pdg.control_deps[&"q3".parse().unwrap()], nodes(["q2"]));
+        assert_eq!(pdg.control_deps[&"q4".parse().unwrap()], nodes(["q2"]));
+        assert_eq!(pdg.control_deps[&"q5".parse().unwrap()], nodes([]));
+    }
+
+    #[test]
+    fn loop_dependences() {
+        let cmds = parse_commands("do x > 0 -> x := x - 1 od; y := x").unwrap();
+        let pg = ProgramGraph::new(Determinism::NonDeterministic, &cmds);
+        let pdg = build_pdg(&pg);
+
+        let body = pg.loop_info(Node::Start).unwrap().body_edges[0].to();
+        assert_eq!(pdg.control_deps[&body], nodes(["qStart"]));
+        assert_eq!(pdg.control_deps[&Node::Start], nodes(["qStart"]));
+        assert_eq!(pdg.data_deps[&body], [body].into_iter().collect());
+    }
+}
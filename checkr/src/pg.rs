@@ -1,5 +1,6 @@
 use std::{
-    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet, VecDeque},
+    str::FromStr,
     sync::atomic::AtomicU64,
 };
 
@@ -7,26 +8,78 @@ use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use tracing::warn;
 
-use crate::ast::{AExpr, BExpr, Command, Commands, Guard, LogicOp, Target};
+use crate::{
+    ast::{AExpr, BExpr, Command, Commands, Guard, LogicOp, Target},
+    dot::DotDocument,
+    parse::{parse_bexpr, parse_commands, ParseError},
+    sign::{Bools, SignMemory},
+};
 
 #[derive(Debug, Clone)]
 pub struct ProgramGraph {
     edges: Vec<Edge>,
     nodes: HashSet<Node>,
     outgoing: HashMap<Node, Vec<Edge>>,
+    incoming: HashMap<Node, Vec<Edge>>,
+    loops: HashMap<Node, LoopInfo>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+/// Information about a loop, keyed by its loop head in [`ProgramGraph`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoopInfo {
+    /// The condition under which the loop is exited.
+    pub exit_condition: BExpr,
+    /// The edges from the loop head into the loop body.
+    pub body_edges: Vec<Edge>,
+    /// The invariant the loop was annotated with, if any.
+    pub invariant: Option<BExpr>,
+    /// The variant the loop was annotated with, if any.
+    pub variant: Option<AExpr>,
+}
+
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
 #[serde(tag = "Case")]
 pub enum Determinism {
     Deterministic,
+    #[default]
     NonDeterministic,
 }
 
+impl std::fmt::Display for Determinism {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Determinism::Deterministic => write!(f, "deterministic"),
+            Determinism::NonDeterministic => write!(f, "non-deterministic"),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid determinism {0:?}, expected `det`, `nondet`, `true` or `false`")]
+pub struct InvalidDeterminism(pub String);
+
+impl FromStr for Determinism {
+    type Err = InvalidDeterminism;
+
+    /// Parses the spellings used on the command line, where `true` means
+    /// deterministic.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "det" | "deterministic" | "true" => Ok(Determinism::Deterministic),
+            "nondet" | "non-deterministic" | "false" => Ok(Determinism::NonDeterministic),
+            _ => Err(InvalidDeterminism(s.to_string())),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct NodeId(u64);
 
+/// Nodes are serialized as their ASCII name, i.e. `qStart`, `q1`, ..., `qFinal`.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
 pub enum Node {
     Start,
     Node(NodeId),
@@ -44,36 +97,83 @@ impl std::fmt::Debug for Node {
 }
 impl std::fmt::Display for Node {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Node::Start => write!(f, "q▷"),
-            Node::Node(n) => write!(
-                f,
-                "q{}",
-                n.0,
-                // n.0.to_string()
-                //     .chars()
-                //     .map(|c| match c {
-                //         '0' => '₀',
-                //         '1' => '₁',
-                //         '2' => '₂',
-                //         '3' => '₃',
-                //         '4' => '₄',
-                //         '5' => '₅',
-                //         '6' => '₆',
-                //         '7' => '₇',
-                //         '8' => '₈',
-                //         '9' => '₉',
-                //         c => c,
-                //     })
-                //     .format("")
-            ),
-            Node::End => write!(f, "q◀"),
+        self.display_with(&NodeStyle::Unicode).fmt(f)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid node name {0:?}")]
+pub struct InvalidNode(pub String);
+
+impl FromStr for Node {
+    type Err = InvalidNode;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "qStart" => Ok(Node::Start),
+            "qFinal" => Ok(Node::End),
+            _ => s
+                .strip_prefix('q')
+                .and_then(|n| n.parse().ok())
+                .map(|n| Node::Node(NodeId(n)))
+                .ok_or_else(|| InvalidNode(s.to_string())),
+        }
+    }
+}
+impl From<Node> for String {
+    fn from(n: Node) -> Self {
+        format!("{n:?}")
+    }
+}
+impl TryFrom<String> for Node {
+    type Error = InvalidNode;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// The style used when rendering node names.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub enum NodeStyle {
+    /// `q▷`, `q1`, ..., `q◀`
+    #[default]
+    Unicode,
+    /// `qStart`, `q1`, ..., `qFinal`
+    Ascii,
+    /// `start`, `{prefix}1`, ..., `end`
+    Custom {
+        start: String,
+        end: String,
+        prefix: String,
+    },
+}
+
+pub struct NodeDisplay<'a> {
+    node: Node,
+    style: &'a NodeStyle,
+}
+
+impl std::fmt::Display for NodeDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.style, self.node) {
+            (NodeStyle::Unicode, Node::Start) => write!(f, "q▷"),
+            (NodeStyle::Unicode, Node::End) => write!(f, "q◀"),
+            (NodeStyle::Ascii, Node::Start) => write!(f, "qStart"),
+            (NodeStyle::Ascii, Node::End) => write!(f, "qFinal"),
+            (NodeStyle::Unicode | NodeStyle::Ascii, Node::Node(n)) => write!(f, "q{}", n.0),
+            (NodeStyle::Custom { start, .. }, Node::Start) => write!(f, "{start}"),
+            (NodeStyle::Custom { end, .. }, Node::End) => write!(f, "{end}"),
+            (NodeStyle::Custom { prefix, .. }, Node::Node(n)) => write!(f, "{prefix}{}", n.0),
         }
     }
 }
 
 static NODE_ID: AtomicU64 = AtomicU64::new(0);
 impl Node {
+    pub fn display_with(self, style: &NodeStyle) -> NodeDisplay<'_> {
+        NodeDisplay { node: self, style }
+    }
     fn fresh() -> Node {
         Node::Node(NodeId(
             NODE_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
@@ -84,7 +184,16 @@ impl Node {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// Actions are serialized with their kind and their GCL source, which is parsed
+/// again when deserializing:
+///
+/// ```json
+/// { "kind": "Assignment", "action": "x := x + 1" }
+/// { "kind": "Skip" }
+/// { "kind": "Condition", "action": "x > 0" }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(into = "ActionJson", try_from = "ActionJson")]
 pub enum Action {
     Assignment(Target<Box<AExpr>>, AExpr),
     Skip,
@@ -100,7 +209,9 @@ impl Action {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// Edges are serialized as `{ "from": .., "action": .., "to": .. }`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(into = "EdgeJson", from = "EdgeJson")]
 pub struct Edge(pub Node, pub Action, pub Node);
 
 impl Edge {
@@ -116,6 +227,99 @@ impl Edge {
     }
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidAction {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("{0:?} is not a single assignment")]
+    NotAnAssignment(String),
+}
+
+impl FromStr for Action {
+    type Err = InvalidAction;
+
+    /// Parses an action as it is displayed, i.e. `skip`, a single assignment,
+    /// or a condition.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_commands(s) {
+            Ok(Commands(cmds)) => match cmds.as_slice() {
+                [Command::Skip] => Ok(Action::Skip),
+                [Command::Assignment(x, a)] => Ok(Action::Assignment(x.clone(), a.clone())),
+                _ => Err(InvalidAction::NotAnAssignment(s.to_string())),
+            },
+            Err(_) => Ok(Action::Condition(parse_bexpr(s)?)),
+        }
+    }
+}
+
+/// Errors from reading a graph with [`ProgramGraph::from_dot`].
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidDot {
+    #[error("failed to parse dot: {0}")]
+    Syntax(String),
+    #[error("{0} are not supported")]
+    Unsupported(&'static str),
+    #[error(transparent)]
+    Node(#[from] InvalidNode),
+    #[error("the edge from {from:?} to {to:?} has no label")]
+    MissingLabel { from: Node, to: Node },
+    #[error("invalid label {label:?}: {source}")]
+    Label {
+        label: String,
+        source: Box<InvalidAction>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", content = "action")]
+enum ActionJson {
+    Assignment(String),
+    Skip,
+    Condition(String),
+}
+
+impl From<Action> for ActionJson {
+    fn from(action: Action) -> Self {
+        match &action {
+            Action::Assignment(_, _) => ActionJson::Assignment(action.to_string()),
+            Action::Skip => ActionJson::Skip,
+            Action::Condition(_) => ActionJson::Condition(action.to_string()),
+        }
+    }
+}
+impl TryFrom<ActionJson> for Action {
+    type Error = InvalidAction;
+
+    fn try_from(action: ActionJson) -> Result<Self, Self::Error> {
+        match action {
+            ActionJson::Assignment(src) => match parse_commands(&src)?.0.as_slice() {
+                [Command::Assignment(x, a)] => Ok(Action::Assignment(x.clone(), a.clone())),
+                _ => Err(InvalidAction::NotAnAssignment(src)),
+            },
+            ActionJson::Skip => Ok(Action::Skip),
+            ActionJson::Condition(src) => Ok(Action::Condition(parse_bexpr(&src)?)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct EdgeJson {
+    from: Node,
+    action: Action,
+    to: Node,
+}
+
+impl From<Edge> for EdgeJson {
+    fn from(Edge(from, action, to): Edge) -> Self {
+        EdgeJson { from, action, to }
+    }
+}
+impl From<EdgeJson> for Edge {
+    fn from(EdgeJson { from, action, to }: EdgeJson) -> Self {
+        Edge(from, action, to)
+    }
+}
+
 impl std::fmt::Display for Action {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -127,14 +331,25 @@ impl std::fmt::Display for Action {
 }
 
 impl Commands {
-    fn edges(&self, det: Determinism, s: Node, t: Node) -> Vec<Edge> {
+    /// The loop invariants of the program together with the heads of their
+    /// loops in the program graph for `det`.
+    pub fn annotations(&self, det: Determinism) -> Vec<(Node, BExpr)> {
+        ProgramGraph::new(det, self).annotations()
+    }
+    fn edges(
+        &self,
+        det: Determinism,
+        loops: &mut HashMap<Node, LoopInfo>,
+        s: Node,
+        t: Node,
+    ) -> Vec<Edge> {
         let mut edges = vec![];
 
         let mut prev = s;
         for (idx, cmd) in self.0.iter().enumerate() {
             let is_last = idx + 1 == self.0.len();
             let next = if is_last { t } else { Node::fresh() };
-            edges.extend(cmd.edges(det, prev, next));
+            edges.extend(cmd.edges(det, loops, prev, next));
             prev = next;
         }
 
@@ -143,7 +358,13 @@ impl Commands {
 }
 
 /// Computes the edges and the condition which is true iff all guards are false
-fn guard_edges(det: Determinism, guards: &[Guard], s: Node, t: Node) -> (Vec<Edge>, BExpr) {
+fn guard_edges(
+    det: Determinism,
+    loops: &mut HashMap<Node, LoopInfo>,
+    guards: &[Guard],
+    s: Node,
+    t: Node,
+) -> (Vec<Edge>, BExpr) {
     match det {
         Determinism::Deterministic => {
             // See the "if" and "do" Commands on Page 25 of Formal Methods
@@ -163,7 +384,7 @@ fn guard_edges(det: Determinism, guards: &[Guard], s: Node, t: Node) -> (Vec<Edg
                     )),
                     q,
                 ));
-                edges.extend(c.edges(det, q, t));
+                edges.extend(c.edges(det, loops, q, t));
                 prev = BExpr::logic(b.to_owned().clone(), LogicOp::Lor, prev);
             }
 
@@ -175,7 +396,7 @@ fn guard_edges(det: Determinism, guards: &[Guard], s: Node, t: Node) -> (Vec<Edg
                 .iter()
                 .flat_map(|Guard(b, c)| {
                     let q = Node::fresh();
-                    let mut edges = c.edges(det, q, t);
+                    let mut edges = c.edges(det, loops, q, t);
                     edges.push(Edge(s, Action::Condition(b.clone()), q));
                     edges
                 })
@@ -186,21 +407,52 @@ fn guard_edges(det: Determinism, guards: &[Guard], s: Node, t: Node) -> (Vec<Edg
 }
 
 impl Command {
-    fn edges(&self, det: Determinism, s: Node, t: Node) -> Vec<Edge> {
+    fn edges(
+        &self,
+        det: Determinism,
+        loops: &mut HashMap<Node, LoopInfo>,
+        s: Node,
+        t: Node,
+    ) -> Vec<Edge> {
         match self {
             Command::Assignment(v, expr) => {
                 vec![Edge(s, Action::Assignment(v.clone(), expr.clone()), t)]
             }
-            Command::Skip => vec![Edge(s, Action::Skip, t)],
-            Command::If(guards) => guard_edges(det, guards, s, t).0,
-            Command::Loop(guards) | Command::EnrichedLoop(_, guards) => {
-                let (mut edges, b) = guard_edges(det, guards, s, s);
+            Command::Skip | Command::Precondition(_) | Command::Postcondition(_) => {
+                vec![Edge(s, Action::Skip, t)]
+            }
+            Command::If(guards) => guard_edges(det, loops, guards, s, t).0,
+            Command::Loop(guards) | Command::EnrichedLoop(_, _, guards) => {
+                let (mut edges, b) = guard_edges(det, loops, guards, s, s);
+                let (invariant, variant) = match self {
+                    Command::EnrichedLoop(i, v, _) => (Some(i.clone()), v.clone()),
+                    _ => (None, None),
+                };
+                loops.insert(
+                    s,
+                    LoopInfo {
+                        exit_condition: b.clone(),
+                        body_edges: edges.iter().filter(|e| e.from() == s).cloned().collect(),
+                        invariant,
+                        variant,
+                    },
+                );
                 edges.push(Edge(s, Action::Condition(b), t));
                 edges
             }
-            Command::Annotated(_, c, _) => c.edges(det, s, t),
+            Command::Annotated(_, c, _) => c.edges(det, loops, s, t),
             Command::Break => todo!(),
             Command::Continue => todo!(),
+            Command::For(_, _, _, _) => self.desugar().edges(det, loops, s, t),
+            Command::Choice(alternatives) => alternatives
+                .iter()
+                .flat_map(|c| {
+                    let q = Node::fresh();
+                    let mut edges = c.edges(det, loops, q, t);
+                    edges.push(Edge(s, Action::Skip, q));
+                    edges
+                })
+                .collect(),
         }
     }
 }
@@ -216,22 +468,68 @@ fn done(guards: &[Guard]) -> BExpr {
 impl ProgramGraph {
     pub fn new(det: Determinism, cmds: &Commands) -> Self {
         Node::reset();
-        let edges = cmds.edges(det, Node::Start, Node::End);
+        let mut loops = HashMap::new();
+        let edges = cmds.edges(det, &mut loops, Node::Start, Node::End);
+        Self::from_edges(edges, loops).rename_with_reverse_post_order()
+    }
+    fn from_edges(edges: Vec<Edge>, loops: HashMap<Node, LoopInfo>) -> Self {
         let mut outgoing: HashMap<Node, Vec<Edge>> = HashMap::new();
+        let mut incoming: HashMap<Node, Vec<Edge>> = HashMap::new();
         let mut nodes: HashSet<Node> = Default::default();
 
         for e in &edges {
             outgoing.entry(e.0).or_default().push(e.clone());
+            incoming.entry(e.2).or_default().push(e.clone());
             nodes.insert(e.0);
             nodes.insert(e.2);
         }
 
         Self {
             outgoing,
+            incoming,
             edges,
             nodes,
+            loops,
+        }
+    }
+    /// Contracts every `skip` edge which is the only edge leaving its source
+    /// and the only edge entering its target, merging the two nodes. Edges
+    /// from [`Node::Start`] or into [`Node::End`] are kept, and the nodes are
+    /// renumbered afterwards.
+    pub fn compress_skips(&self) -> Self {
+        let mut edges = self.edges.clone();
+        let mut loops = self.loops.clone();
+
+        let is_contractible = |edges: &[Edge], e: &Edge| {
+            matches!(e.action(), Action::Skip)
+                && e.from() != e.to()
+                && [e.from(), e.to()]
+                    .iter()
+                    .all(|n| matches!(n, Node::Node(_)))
+                && edges.iter().filter(|f| f.from() == e.from()).count() == 1
+                && edges.iter().filter(|f| f.to() == e.to()).count() == 1
+        };
+        while let Some(idx) = edges.iter().position(|e| is_contractible(&edges, e)) {
+            let Edge(s, _, t) = edges.remove(idx);
+            let rename = |Edge(a, action, b): &Edge| {
+                let rename = |n: Node| if n == t { s } else { n };
+                Edge(rename(*a), action.clone(), rename(*b))
+            };
+            edges = edges.iter().map(rename).collect();
+            loops = loops
+                .into_iter()
+                .map(|(head, info)| {
+                    let head = if head == t { s } else { head };
+                    let info = LoopInfo {
+                        body_edges: info.body_edges.iter().map(rename).collect(),
+                        ..info
+                    };
+                    (head, info)
+                })
+                .collect();
         }
-        .rename_with_reverse_post_order()
+
+        Self::from_edges(edges, loops).rename_with_reverse_post_order()
     }
     pub fn edges(&self) -> &[Edge] {
         &self.edges
@@ -245,28 +543,235 @@ impl ProgramGraph {
             .map(|s| s.as_slice())
             .unwrap_or_default()
     }
+    pub fn incoming(&self, node: Node) -> &[Edge] {
+        self.incoming
+            .get(&node)
+            .map(|s| s.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The loops of the graph, keyed by their loop head.
+    pub fn loops(&self) -> &HashMap<Node, LoopInfo> {
+        &self.loops
+    }
+    /// The invariants of the annotated loops, keyed by their loop heads.
+    pub fn annotations(&self) -> Vec<(Node, BExpr)> {
+        self.loops
+            .iter()
+            .filter_map(|(&n, info)| Some((n, info.invariant.clone()?)))
+            .sorted_by_key(|(n, _)| *n)
+            .collect()
+    }
+    pub fn loop_info(&self, node: Node) -> Option<&LoopInfo> {
+        self.loops.get(&node)
+    }
 
     pub fn fv(&self) -> HashSet<Target> {
         self.edges.iter().flat_map(|e| e.action().fv()).collect()
     }
 
+    /// The nodes from which `node` can be reached, including `node` itself.
+    pub fn backward_reachable_from(&self, node: Node) -> HashSet<Node> {
+        let mut visited: HashSet<Node> = [node].into_iter().collect();
+        let mut queue = VecDeque::from([node]);
+
+        while let Some(n) = queue.pop_front() {
+            for e in self.incoming(n) {
+                if visited.insert(e.from()) {
+                    queue.push_back(e.from());
+                }
+            }
+        }
+
+        visited
+    }
+    /// The dead-end nodes of the graph, from which no path leads to
+    /// [`Node::End`], such that executions reaching them never terminate.
+    pub fn nodes_that_cannot_reach_end(&self) -> HashSet<Node> {
+        let reaching = self.backward_reachable_from(Node::End);
+        self.nodes.difference(&reaching).copied().collect()
+    }
+    /// The edges `(from, to)` which go back to an ancestor of `from` in a
+    /// depth-first traversal starting in [`Node::Start`], such that every
+    /// cycle in the graph contains at least one of them.
+    pub fn loop_back_edges(&self) -> HashSet<(Node, Node)> {
+        let mut back_edges = HashSet::new();
+        let mut visited: HashSet<Node> = [Node::Start].into_iter().collect();
+        let mut on_stack: HashSet<Node> = [Node::Start].into_iter().collect();
+        let mut stack = vec![(Node::Start, 0)];
+
+        while let Some((n, i)) = stack.last_mut() {
+            let n = *n;
+            let Some(e) = self.outgoing(n).get(*i) else {
+                on_stack.remove(&n);
+                stack.pop();
+                continue;
+            };
+            *i += 1;
+            if on_stack.contains(&e.to()) {
+                back_edges.insert((n, e.to()));
+            } else if visited.insert(e.to()) {
+                on_stack.insert(e.to());
+                stack.push((e.to(), 0));
+            }
+        }
+
+        back_edges
+    }
+
+    /// Exports the graph as JSON of the form
+    ///
+    /// ```json
+    /// {
+    ///   "nodes": ["qStart", "q1", "qFinal"],
+    ///   "edges": [{ "from": "qStart", "action": { .. }, "to": "q1" }, ..]
+    /// }
+    /// ```
+    ///
+    /// where the nodes are sorted, and the actions are serialized as described
+    /// in [`Action`]. Loop information is not included in the export.
+    pub fn to_json(&self) -> String {
+        let json = ProgramGraphJson {
+            nodes: self.nodes.iter().copied().sorted().collect(),
+            edges: self.edges.clone(),
+        };
+        serde_json::to_string_pretty(&json).expect("program graphs are serializable")
+    }
+    /// Reads a graph exported with [`ProgramGraph::to_json`].
+    pub fn from_json(src: &str) -> serde_json::Result<Self> {
+        let json: ProgramGraphJson = serde_json::from_str(src)?;
+        let mut outgoing: HashMap<Node, Vec<Edge>> = HashMap::new();
+        let mut incoming: HashMap<Node, Vec<Edge>> = HashMap::new();
+        for e in &json.edges {
+            outgoing.entry(e.0).or_default().push(e.clone());
+            incoming.entry(e.2).or_default().push(e.clone());
+        }
+        Ok(Self {
+            edges: json.edges,
+            nodes: json.nodes.into_iter().collect(),
+            outgoing,
+            incoming,
+            loops: Default::default(),
+        })
+    }
+
+    /// Reads a graph in the format of [`ProgramGraph::dot`], where the nodes
+    /// are named as described in [`Node`] and every edge is labelled with its
+    /// action. Node statements, attributes other than edge labels, and
+    /// comments are ignored. Loop information is not recovered.
+    pub fn from_dot(src: &str) -> Result<Self, InvalidDot> {
+        use graphviz_rust::dot_structures::{EdgeTy, Graph, Id, Stmt, Vertex};
+
+        let unquote = |id: &Id| match id {
+            Id::Escaped(s) => s
+                .strip_prefix('"')
+                .and_then(|s| s.strip_suffix('"'))
+                .unwrap_or(s)
+                .replace("\\\"", "\""),
+            Id::Html(s) | Id::Plain(s) | Id::Anonymous(s) => s.clone(),
+        };
+
+        let stmts = match graphviz_rust::parse(src).map_err(InvalidDot::Syntax)? {
+            Graph::DiGraph { stmts, .. } => stmts,
+            Graph::Graph { .. } => return Err(InvalidDot::Unsupported("undirected graphs")),
+        };
+        let mut edges = vec![];
+        for stmt in stmts {
+            let e = match stmt {
+                Stmt::Edge(e) => e,
+                Stmt::Subgraph(_) => return Err(InvalidDot::Unsupported("subgraphs")),
+                Stmt::Node(_) | Stmt::Attribute(_) | Stmt::GAttribute(_) => continue,
+            };
+            let vertices = match e.ty {
+                EdgeTy::Pair(a, b) => vec![a, b],
+                EdgeTy::Chain(vs) => vs,
+            };
+            let nodes: Vec<Node> = vertices
+                .iter()
+                .map(|v| match v {
+                    Vertex::N(n) => Ok(unquote(&n.0).parse()?),
+                    Vertex::S(_) => Err(InvalidDot::Unsupported("subgraphs")),
+                })
+                .try_collect()?;
+            for (&from, &to) in nodes.iter().tuple_windows() {
+                let label = e
+                    .attributes
+                    .iter()
+                    .find(|a| unquote(&a.0) == "label")
+                    .map(|a| unquote(&a.1))
+                    .ok_or(InvalidDot::MissingLabel { from, to })?;
+                let action = label.parse().map_err(|source| InvalidDot::Label {
+                    label,
+                    source: Box::new(source),
+                })?;
+                edges.push(Edge(from, action, to));
+            }
+        }
+        Ok(Self::from_edges(edges, HashMap::new()))
+    }
+
     pub fn dot(&self) -> String {
-        format!(
-            "digraph G {{\n{}\n}}",
-            self.edges
+        self.dot_with(&NodeStyle::Ascii)
+    }
+    pub fn dot_with(&self, style: &NodeStyle) -> String {
+        self.dot_document("G", style).to_string()
+    }
+    /// The graph as a [`DotDocument`] called `name`, which can be given a
+    /// header and global style before it is rendered.
+    pub fn dot_document(&self, name: &str, style: &NodeStyle) -> DotDocument {
+        self.dot_with_labels(name, |n| n.display_with(style).to_string())
+    }
+    /// Renders the graph with the given node labels. Join points are drawn as
+    /// diamonds, and nodes which cannot reach the end are colored red.
+    fn dot_with_labels(&self, name: &str, label: impl Fn(Node) -> String) -> DotDocument {
+        let join_points = find_join_points(self);
+        let dead_ends = self.nodes_that_cannot_reach_end();
+        let node = |n: Node| {
+            let shape = if join_points.contains_key(&n) {
+                ", shape=diamond"
+            } else {
+                ""
+            };
+            let color = if dead_ends.contains(&n) {
+                ", color=red"
+            } else {
+                ""
+            };
+            format!("{n:?}[label={:?}{shape}{color}]", label(n))
+        };
+        DotDocument::new(name).statements(self.edges.iter().map(|e| {
+            format!(
+                "{}; {:?} -> {:?}[label={:?}]; {};",
+                node(e.0),
+                e.0,
+                e.2,
+                e.1.to_string(),
+                node(e.2),
+            )
+        }))
+    }
+
+    /// Attaches custom `labels` to the nodes of the graph, which are used
+    /// instead of the default names when rendering it.
+    pub fn with_labels(&self, labels: &HashMap<Node, String>) -> LabeledProgramGraph<'_> {
+        LabeledProgramGraph {
+            pg: self,
+            labels: labels.clone(),
+        }
+    }
+    /// Labels every node with the result of an analysis, such as the facts
+    /// computed by [`mono_analysis`](crate::analysis::mono_analysis).
+    pub fn label_with_annotations(
+        &self,
+        analysis: &HashMap<Node, impl std::fmt::Display>,
+    ) -> LabeledProgramGraph<'_> {
+        LabeledProgramGraph {
+            pg: self,
+            labels: analysis
                 .iter()
-                .map(|e| format!(
-                    "  {:?}[label=\"{}\"]; {:?} -> {:?}[label={:?}]; {:?}[label=\"{}\"];",
-                    e.0,
-                    e.0,
-                    e.0,
-                    e.2,
-                    e.1.to_string(),
-                    e.2,
-                    e.2,
-                ))
-                .format("  \n")
-        )
+                .map(|(n, a)| (*n, format!("{n}\n{a}")))
+                .collect(),
+        }
     }
 
     pub fn as_petgraph(
@@ -294,6 +799,28 @@ impl ProgramGraph {
         (g, node_mapping, node_mapping_rev)
     }
 
+    /// The strongly connected components of the graph in reverse topological
+    /// order of the condensation, i.e. [`Node::End`] is in the first component.
+    pub fn strongly_connected_components(&self) -> Vec<HashSet<Node>> {
+        let (g, _, node_mapping_rev) = self.as_petgraph();
+
+        petgraph::algo::tarjan_scc(&g)
+            .into_iter()
+            .map(|scc| scc.iter().map(|n| node_mapping_rev[n]).collect())
+            .collect()
+    }
+    /// Computes the strongly connected components once, such that nodes can be
+    /// looked up in them repeatedly.
+    pub fn scc_index(&self) -> SccIndex {
+        let components = self.strongly_connected_components();
+        let index = components
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, scc)| scc.iter().map(move |n| (*n, idx)))
+            .collect();
+        SccIndex { components, index }
+    }
+
     pub fn rename_with_reverse_post_order(&self) -> Self {
         let (g, node_mapping, node_mapping_rev) = self.as_petgraph();
 
@@ -339,14 +866,12 @@ impl ProgramGraph {
             }
         }
 
+        let rename_edge = |Edge(a, action, b): &Edge| {
+            Edge(node_mapping_new[a], action.clone(), node_mapping_new[b])
+        };
+
         Self {
-            edges: self
-                .edges
-                .iter()
-                .map(|Edge(a, action, b)| {
-                    Edge(node_mapping_new[a], action.clone(), node_mapping_new[b])
-                })
-                .collect(),
+            edges: self.edges.iter().map(rename_edge).collect(),
             nodes: node_mapping_new.values().copied().collect(),
             outgoing: self
                 .outgoing
@@ -354,15 +879,583 @@ impl ProgramGraph {
                 .map(|(n, outgoing)| {
                     (
                         node_mapping_new[n],
-                        outgoing
-                            .iter()
-                            .map(|Edge(a, action, b)| {
-                                Edge(node_mapping_new[a], action.clone(), node_mapping_new[b])
-                            })
-                            .collect(),
+                        outgoing.iter().map(rename_edge).collect(),
+                    )
+                })
+                .collect(),
+            incoming: self
+                .incoming
+                .iter()
+                .map(|(n, incoming)| {
+                    (
+                        node_mapping_new[n],
+                        incoming.iter().map(rename_edge).collect(),
                     )
                 })
                 .collect(),
+            loops: self
+                .loops
+                .iter()
+                .map(|(n, info)| {
+                    (
+                        node_mapping_new[n],
+                        LoopInfo {
+                            exit_condition: info.exit_condition.clone(),
+                            body_edges: info.body_edges.iter().map(rename_edge).collect(),
+                            invariant: info.invariant.clone(),
+                            variant: info.variant.clone(),
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The nodes where several control flow paths merge, mapped to their
+/// predecessors in sorted order. These are the nodes where analyses such as
+/// the sign analysis join abstract states, and thus might lose precision.
+pub fn find_join_points(pg: &ProgramGraph) -> HashMap<Node, Vec<Node>> {
+    pg.incoming
+        .iter()
+        .filter(|(_, incoming)| incoming.len() > 1)
+        .map(|(n, incoming)| (*n, incoming.iter().map(|e| e.from()).sorted().collect()))
+        .collect()
+}
+
+/// The minimum number of steps needed to reach each node from `from`, for
+/// the nodes which can be reached in at most `max_steps` steps.
+pub fn reachable_within_steps(
+    pg: &ProgramGraph,
+    from: Node,
+    max_steps: usize,
+) -> HashMap<Node, usize> {
+    let mut distances = HashMap::from([(from, 0)]);
+    let mut queue = VecDeque::from([from]);
+
+    while let Some(n) = queue.pop_front() {
+        let d = distances[&n];
+        if d == max_steps {
+            continue;
+        }
+        for e in pg.outgoing(n) {
+            if let Entry::Vacant(entry) = distances.entry(e.to()) {
+                entry.insert(d + 1);
+                queue.push_back(e.to());
+            }
+        }
+    }
+
+    distances
+}
+/// Whether [`Node::End`] can be reached from `from` in at most `max_steps`
+/// steps, ignoring the conditions on the edges.
+pub fn can_reach_end_within(pg: &ProgramGraph, from: Node, max_steps: usize) -> bool {
+    reachable_within_steps(pg, from, max_steps).contains_key(&Node::End)
+}
+
+/// A [`ProgramGraph`] with custom labels for some of its nodes, created by
+/// [`ProgramGraph::with_labels`].
+/// The strongly connected components of a [`ProgramGraph`], computed by
+/// [`ProgramGraph::scc_index`].
+#[derive(Debug, Clone)]
+pub struct SccIndex {
+    components: Vec<HashSet<Node>>,
+    index: HashMap<Node, usize>,
+}
+
+impl SccIndex {
+    /// The index of the component of `n` in
+    /// [`ProgramGraph::strongly_connected_components`], if it is in the graph.
+    pub fn component(&self, n: Node) -> Option<usize> {
+        self.index.get(&n).copied()
+    }
+    pub fn same_scc(&self, n1: Node, n2: Node) -> bool {
+        matches!((self.component(n1), self.component(n2)), (Some(a), Some(b)) if a == b)
+    }
+    pub fn nodes_in_scc(&self, scc: usize) -> Option<&HashSet<Node>> {
+        self.components.get(scc)
+    }
+    pub fn components(&self) -> &[HashSet<Node>] {
+        &self.components
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LabeledProgramGraph<'a> {
+    pg: &'a ProgramGraph,
+    labels: HashMap<Node, String>,
+}
+
+impl LabeledProgramGraph<'_> {
+    pub fn label(&self, node: Node) -> Option<&str> {
+        self.labels.get(&node).map(|l| l.as_str())
+    }
+    /// Renders the graph, falling back to the default name for nodes without
+    /// a label.
+    pub fn dot(&self) -> String {
+        self.pg
+            .dot_with_labels("G", |n| match self.label(n) {
+                Some(l) => l.to_string(),
+                None => n.display_with(&NodeStyle::Ascii).to_string(),
+            })
+            .to_string()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ProgramGraphJson {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+/// A [`ProgramGraph`] where the edges are annotated with a cost, such as the
+/// time it takes to execute them. Edges without a weight have cost `0`.
+#[derive(Debug, Clone)]
+pub struct WeightedProgramGraph {
+    pub pg: ProgramGraph,
+    pub weights: HashMap<(Node, Node), f64>,
+}
+
+impl WeightedProgramGraph {
+    pub fn new(pg: ProgramGraph, weights: HashMap<(Node, Node), f64>) -> Self {
+        Self { pg, weights }
+    }
+    pub fn weight(&self, edge: &Edge) -> f64 {
+        self.weights
+            .get(&(edge.from(), edge.to()))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Computes the most expensive path from [`Node::Start`] to [`Node::End`]
+    /// together with its total cost.
+    ///
+    /// This runs Bellman-Ford on the negated weights, and thus returns `None`
+    /// if the end is unreachable or if a reachable cycle has a positive cost,
+    /// in which case the cost is unbounded.
+    pub fn longest_weighted_path(&self) -> Option<(f64, Vec<Edge>)> {
+        let mut dist: HashMap<Node, f64> = [(Node::Start, 0.0)].into_iter().collect();
+        let mut pred: HashMap<Node, &Edge> = HashMap::new();
+
+        for _ in 1..self.pg.nodes().len() {
+            let mut changed = false;
+            for e in self.pg.edges() {
+                let Some(&d) = dist.get(&e.from()) else {
+                    continue;
+                };
+                let new = d - self.weight(e);
+                if !matches!(dist.get(&e.to()), Some(&old) if old <= new) {
+                    dist.insert(e.to(), new);
+                    pred.insert(e.to(), e);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let has_positive_cycle = self.pg.edges().iter().any(|e| match dist.get(&e.from()) {
+            Some(&d) => dist[&e.to()] > d - self.weight(e),
+            None => false,
+        });
+        if has_positive_cycle {
+            return None;
+        }
+
+        let cost = -*dist.get(&Node::End)?;
+        let mut path = vec![];
+        let mut current = Node::End;
+        while let Some(e) = pred.get(&current) {
+            path.push((*e).clone());
+            current = e.from();
+        }
+        path.reverse();
+
+        Some((cost, path))
+    }
+}
+
+/// Computes a condition on the values of the variables which is true iff
+/// `edge` is enabled.
+///
+/// The `analysis_result` is the result of a sign analysis on `pg`, and is used
+/// to rule out edges which can never be taken. `None` is returned if `edge` is
+/// not in `pg`, if its source node is unreachable, or if its condition is false
+/// in all of the reachable sign memories.
+pub fn edge_triggering_condition(
+    pg: &ProgramGraph,
+    edge: &Edge,
+    analysis_result: &HashMap<Node, HashSet<SignMemory>>,
+) -> Option<BExpr> {
+    if !pg.outgoing(edge.from()).contains(edge) {
+        return None;
+    }
+
+    let mems = analysis_result.get(&edge.from())?;
+
+    match edge.action() {
+        Action::Assignment(_, _) | Action::Skip if !mems.is_empty() => Some(BExpr::Bool(true)),
+        Action::Condition(b)
+            if mems
+                .iter()
+                .any(|mem| b.semantics_sign(mem).contains(Bools::TRUE)) =>
+        {
+            Some(b.clone())
         }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::{
+        can_reach_end_within, edge_triggering_condition, find_join_points, reachable_within_steps,
+        Action, Determinism, Edge, Node, NodeId, NodeStyle, ProgramGraph, WeightedProgramGraph,
+    };
+    use crate::{
+        analysis::{mono_analysis, FiFo},
+        ast::BExpr,
+        parse::{parse_bexpr, parse_commands},
+        sign::{Sign, SignAnalysis, SignMemory, Signs},
+    };
+
+    #[test]
+    fn node_styles() {
+        let custom = NodeStyle::Custom {
+            start: "Start".to_string(),
+            end: "End".to_string(),
+            prefix: "n".to_string(),
+        };
+        let render = |style: &NodeStyle| {
+            [Node::Start, Node::Node(NodeId(3)), Node::End]
+                .map(|n| n.display_with(style).to_string())
+        };
+
+        assert_eq!(render(&NodeStyle::Unicode), ["q▷", "q3", "q◀"]);
+        assert_eq!(render(&NodeStyle::Ascii), ["qStart", "q3", "qFinal"]);
+        assert_eq!(render(&custom), ["Start", "n3", "End"]);
+        assert_eq!(Node::Start.to_string(), "q▷");
+    }
+
+    #[test]
+    fn determinism_spellings() {
+        for (s, det) in [
+            ("det", Determinism::Deterministic),
+            ("deterministic", Determinism::Deterministic),
+            ("true", Determinism::Deterministic),
+            ("nondet", Determinism::NonDeterministic),
+            ("non-deterministic", Determinism::NonDeterministic),
+            ("false", Determinism::NonDeterministic),
+        ] {
+            assert_eq!(s.parse::<Determinism>().unwrap(), det);
+        }
+        assert!("maybe".parse::<Determinism>().is_err());
+
+        for det in [Determinism::Deterministic, Determinism::NonDeterministic] {
+            assert_eq!(det.to_string().parse::<Determinism>().unwrap(), det);
+        }
+        assert_eq!(Determinism::default(), Determinism::NonDeterministic);
+    }
+
+    #[test]
+    fn dot_uses_ascii_by_default() {
+        let cmds = parse_commands("x := 1; skip").unwrap();
+        let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+
+        assert_eq!(
+            pg.dot(),
+            "digraph G {\n  qStart[label=\"qStart\"]; qStart -> q1[label=\"x := 1\"]; q1[label=\"q1\"];\n  q1[label=\"q1\"]; q1 -> qFinal[label=\"skip\"]; qFinal[label=\"qFinal\"];\n}"
+        );
+        assert_eq!(
+            pg.dot_with(&NodeStyle::Unicode),
+            "digraph G {\n  qStart[label=\"q▷\"]; qStart -> q1[label=\"x := 1\"]; q1[label=\"q1\"];\n  q1[label=\"q1\"]; q1 -> qFinal[label=\"skip\"]; qFinal[label=\"q◀\"];\n}"
+        );
+    }
+
+    #[test]
+    fn nested_loop_exit_conditions() {
+        let cmds = parse_commands(
+            "do x > 0 -> do y > 0 -> y := y - 1 od; x := x - 1 od; do z = 0 -> skip od",
+        )
+        .unwrap();
+        let pg = ProgramGraph::new(Determinism::NonDeterministic, &cmds);
+
+        assert_eq!(pg.loops().len(), 3);
+
+        let outer = pg.loop_info(Node::Start).unwrap();
+        assert_eq!(outer.exit_condition, parse_bexpr("!(x > 0)").unwrap());
+        assert_eq!(outer.body_edges.len(), 1);
+
+        let inner = pg.loop_info(outer.body_edges[0].to()).unwrap();
+        assert_eq!(inner.exit_condition, parse_bexpr("!(y > 0)").unwrap());
+
+        let last = pg
+            .edges()
+            .iter()
+            .find(|e| {
+                e.from() == Node::Start
+                    && e.action() == &Action::Condition(outer.exit_condition.clone())
+            })
+            .unwrap()
+            .to();
+        assert_eq!(
+            pg.loop_info(last).unwrap().exit_condition,
+            parse_bexpr("!(z = 0)").unwrap()
+        );
+    }
+
+    #[test]
+    fn triggering_conditions() {
+        let cmds = parse_commands("if x > 0 -> y := 1 [] x < 0 -> y := 2 fi").unwrap();
+        let pg = ProgramGraph::new(Determinism::NonDeterministic, &cmds);
+        let assignment =
+            SignMemory::from_targets(cmds.fv(), |_| Sign::Positive, |_| Signs::POSITIVE);
+        let facts = mono_analysis::<_, FiFo>(
+            SignAnalysis {
+                assignment,
+                lengths: Default::default(),
+            },
+            &pg,
+        )
+        .facts;
+
+        let condition = |e: &Edge| edge_triggering_condition(&pg, e, &facts);
+
+        for e in pg.outgoing(Node::Start) {
+            let Action::Condition(b) = e.action() else {
+                unreachable!()
+            };
+            if b == &parse_bexpr("x > 0").unwrap() {
+                assert_eq!(condition(e), Some(b.clone()));
+                let assignment = &pg.outgoing(e.to())[0];
+                assert_eq!(condition(assignment), Some(BExpr::Bool(true)));
+            } else {
+                assert_eq!(condition(e), None);
+                assert_eq!(condition(&pg.outgoing(e.to())[0]), None);
+            }
+        }
+    }
+
+    #[test]
+    fn longest_weighted_path() {
+        let cmds = parse_commands("if x > 0 -> x := 1; x := 2 [] x < 0 -> skip fi").unwrap();
+        let pg = ProgramGraph::new(Determinism::NonDeterministic, &cmds);
+        let weights = pg
+            .edges()
+            .iter()
+            .map(|e| {
+                let w = match e.action() {
+                    Action::Assignment(_, _) => 2.0,
+                    Action::Skip => 3.0,
+                    Action::Condition(_) => 1.0,
+                };
+                ((e.from(), e.to()), w)
+            })
+            .collect();
+        let wpg = WeightedProgramGraph::new(pg, weights);
+
+        let (cost, path) = wpg.longest_weighted_path().unwrap();
+        assert_eq!(cost, 5.0);
+        assert_eq!(path.len(), 3);
+        assert_eq!(path[0].from(), Node::Start);
+        assert_eq!(path[2].to(), Node::End);
+
+        let cmds = parse_commands("do true -> x := 1 od").unwrap();
+        let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+        let weights = pg
+            .edges()
+            .iter()
+            .map(|e| ((e.from(), e.to()), 1.0))
+            .collect();
+        assert_eq!(
+            WeightedProgramGraph::new(pg, weights).longest_weighted_path(),
+            None
+        );
+    }
+
+    #[test]
+    fn scc_index() {
+        let cmds = parse_commands("x := 1; do x > 0 -> x := x - 1 od; y := 2").unwrap();
+        let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+        let index = pg.scc_index();
+
+        assert_eq!(
+            index.components().iter().map(|c| c.len()).sum::<usize>(),
+            pg.nodes().len()
+        );
+        assert_eq!(index.component(Node::End), Some(0));
+
+        let head = *pg.loops().keys().next().unwrap();
+        let body = pg.loop_info(head).unwrap().body_edges[0].to();
+        assert!(index.same_scc(head, body));
+        assert!(!index.same_scc(Node::Start, head));
+        assert_eq!(
+            index.nodes_in_scc(index.component(head).unwrap()),
+            Some(&[head, body].into_iter().collect())
+        );
+        assert_eq!(
+            index
+                .nodes_in_scc(index.component(Node::Start).unwrap())
+                .map(|c| c.len()),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let cmds = parse_commands("if x > 0 -> A[x] := x - 1 [] true -> skip fi").unwrap();
+        let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+        let json = pg.to_json();
+
+        assert_eq!(
+            serde_json::to_string(&pg.edges()[1]).unwrap(),
+            r#"{"from":"q2","action":{"kind":"Assignment","action":"A[x] := (x - 1)"},"to":"qFinal"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&pg.edges()[3]).unwrap(),
+            r#"{"from":"q1","action":{"kind":"Skip"},"to":"qFinal"}"#
+        );
+
+        let read = ProgramGraph::from_json(&json).unwrap();
+        assert_eq!(read.edges(), pg.edges());
+        assert_eq!(read.nodes(), pg.nodes());
+        assert_eq!(read.outgoing(Node::Start), pg.outgoing(Node::Start));
+        assert_eq!(read.incoming(Node::End), pg.incoming(Node::End));
+        assert_eq!(read.to_json(), json);
+
+        assert!(ProgramGraph::from_json(r#"{ "nodes": ["q"], "edges": [] }"#).is_err());
+    }
+
+    #[test]
+    fn labeled_dot() {
+        let cmds = parse_commands("x := 1; skip").unwrap();
+        let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+        let labels = [(Node::Start, "entry".to_string())].into_iter().collect();
+
+        assert_eq!(
+            pg.with_labels(&labels).dot(),
+            "digraph G {\n  qStart[label=\"entry\"]; qStart -> q1[label=\"x := 1\"]; q1[label=\"q1\"];\n  q1[label=\"q1\"]; q1 -> qFinal[label=\"skip\"]; qFinal[label=\"qFinal\"];\n}"
+        );
+
+        let analysis = [(Node::Node(NodeId(1)), "x = 1")].into_iter().collect();
+        let labeled = pg.label_with_annotations(&analysis);
+        assert_eq!(labeled.label(Node::Node(NodeId(1))), Some("q1\nx = 1"));
+        assert!(labeled.dot().contains("q1[label=\"q1\\nx = 1\"]"));
+    }
+
+    #[test]
+    fn join_points() {
+        let cmds = parse_commands("if x > 0 -> y := 1 [] x <= 0 -> y := 2 fi; z := y").unwrap();
+        let pg = ProgramGraph::new(Determinism::NonDeterministic, &cmds);
+        let joins = find_join_points(&pg);
+
+        assert_eq!(joins.len(), 1);
+        let (join, preds) = joins.iter().next().unwrap();
+        assert_eq!(preds.len(), 2);
+        assert_eq!(pg.incoming(*join).len(), 2);
+        assert!(pg.incoming(Node::Start).is_empty());
+        assert_eq!(pg.outgoing(*join)[0].action().to_string(), "z := y");
+        assert!(pg
+            .dot()
+            .contains(&format!("{join:?}[label=\"{join:?}\", shape=diamond]")));
+    }
+
+    #[test]
+    fn choice() {
+        let cmds = parse_commands("(x := 1 [] x := 2; y := x [] skip)").unwrap();
+        assert_eq!(parse_commands(&cmds.to_string()).unwrap(), cmds);
+
+        let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+        let choices = pg.outgoing(Node::Start);
+        assert_eq!(choices.len(), 3);
+        assert!(choices.iter().all(|e| e.action() == &Action::Skip));
+        assert_eq!(pg.incoming(Node::End).len(), 3);
+        assert_eq!(
+            pg.edges()
+                .iter()
+                .map(|e| e.action().to_string())
+                .sorted()
+                .collect_vec(),
+            ["skip", "skip", "skip", "skip", "x := 1", "x := 2", "y := x"]
+        );
+    }
+
+    #[test]
+    fn reachability_with_fuel() {
+        let cmds = parse_commands("x := 1; do x > 0 -> x := x - 1 od; y := x").unwrap();
+        let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+        let q1 = Node::Node(NodeId(1));
+
+        let distances = reachable_within_steps(&pg, Node::Start, 2);
+        assert_eq!(distances.len(), 4);
+        assert_eq!(distances[&Node::Start], 0);
+        assert_eq!(distances[&q1], 1);
+        assert!(!distances.contains_key(&Node::End));
+
+        assert_eq!(reachable_within_steps(&pg, Node::Start, 3)[&Node::End], 3);
+        assert!(can_reach_end_within(&pg, Node::Start, 3));
+        assert!(!can_reach_end_within(&pg, Node::Start, 2));
+        assert!(can_reach_end_within(&pg, Node::End, 0));
+    }
+
+    #[test]
+    fn dead_ends() {
+        let pg = ProgramGraph::from_json(
+            r#"{
+                "nodes": ["qStart", "q1", "qFinal"],
+                "edges": [
+                    { "from": "qStart", "action": { "kind": "Condition", "action": "x > 0" }, "to": "q1" },
+                    { "from": "q1", "action": { "kind": "Skip" }, "to": "q1" },
+                    { "from": "qStart", "action": { "kind": "Condition", "action": "x <= 0" }, "to": "qFinal" }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let q1: Node = "q1".parse().unwrap();
+
+        assert_eq!(
+            pg.backward_reachable_from(q1),
+            [Node::Start, q1].into_iter().collect()
+        );
+        assert_eq!(pg.nodes_that_cannot_reach_end(), [q1].into_iter().collect());
+        assert!(pg
+            .dot()
+            .contains("q1[label=\"q1\", shape=diamond, color=red]"));
+
+        let cmds = parse_commands("do x > 0 -> x := x - 1 od").unwrap();
+        let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+        assert!(pg.nodes_that_cannot_reach_end().is_empty());
+    }
+
+    #[test]
+    fn back_edges() {
+        let cmds =
+            parse_commands("do x > 0 -> do y > 0 -> y := y - 1 od; x := x - 1 od; z := 1").unwrap();
+        let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+        let back_edges = pg.loop_back_edges();
+        let index = pg.scc_index();
+
+        assert_eq!(back_edges.len(), 2);
+        for &(from, to) in &back_edges {
+            assert!(pg.loops().contains_key(&to));
+            assert!(index.same_scc(from, to));
+        }
+        assert!(back_edges.iter().any(|&(_, to)| to == Node::Start));
+    }
+
+    #[test]
+    fn compressed_skips() {
+        let cmds = parse_commands("skip; skip; x := 1").unwrap();
+        let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+        assert_eq!(pg.edges().len(), 3);
+
+        let compressed = pg.compress_skips();
+        assert_eq!(compressed.edges().len(), 2);
+        assert_eq!(compressed.nodes().len(), 3);
+        assert!(compressed.nodes().contains(&Node::Node(NodeId(1))));
     }
 }
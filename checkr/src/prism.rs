@@ -0,0 +1,239 @@
+//! Export of program graphs as PRISM Markov decision processes.
+//!
+//! A program graph is translated into a single module with a program counter
+//! `pc` and an integer variable for each program variable. Every node becomes
+//! a command which picks one of its outgoing edges with the given probability,
+//! and applies the assignment of the edge if it has one:
+//!
+//! ```text
+//! [] pc=1 -> 0.25:(pc'=2)&(x'=(x + 1)) + 0.75:(pc'=3);
+//! ```
+//!
+//! Conditions are abstracted away by the probabilities, so the probability of
+//! a branch should reflect how often its guard holds. Since the program
+//! variables are unbounded, the model must be checked with PRISM's explicit
+//! engine.
+
+use std::collections::{BTreeMap, HashMap};
+
+use itertools::Itertools;
+
+use crate::{
+    ast::{AExpr, AOp, Function, Target, Variable},
+    pg::{Action, Edge, Node, ProgramGraph},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PrismError {
+    #[error("arrays are not supported in the PRISM export")]
+    Array,
+    #[error("{0} is not supported in the PRISM export")]
+    Unsupported(String),
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ProbError {
+    #[error("the probability {prob} of the edge from {from} to {to} is not in [0, 1]")]
+    OutOfRange { from: Node, to: Node, prob: f64 },
+    #[error("there is no edge from {from} to {to}")]
+    UnknownEdge { from: Node, to: Node },
+    #[error("the probabilities of the edges from {node} sum to {sum}, not 1")]
+    SumNotOne { node: Node, sum: f64 },
+}
+
+const EPSILON: f64 = 1e-9;
+
+/// Translates `pg` into a PRISM MDP, where the edges between two nodes are
+/// taken with the probabilities in `transition_probs`. The edges without a
+/// probability share what is left of the probability of their source evenly.
+pub fn pg_to_prism(
+    pg: &ProgramGraph,
+    transition_probs: &HashMap<(Node, Node), f64>,
+) -> Result<String, PrismError> {
+    let vars = pg
+        .fv()
+        .into_iter()
+        .map(|t| match t {
+            Target::Variable(v) => Ok(v),
+            Target::Array(_, ()) => Err(PrismError::Array),
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .sorted()
+        .collect_vec();
+    // NOTE: Nodes are ordered start, middle nodes, end, such that the start
+    // node is always `pc=0`
+    let pcs: BTreeMap<Node, usize> = pg
+        .nodes()
+        .iter()
+        .copied()
+        .sorted()
+        .enumerate()
+        .map(|(pc, n)| (n, pc))
+        .collect();
+
+    let mut lines = vec![
+        "mdp".to_string(),
+        String::new(),
+        "module program".to_string(),
+        format!("  pc : [0..{}] init 0;", pcs.len().saturating_sub(1)),
+    ];
+    lines.extend(vars.iter().map(|v| format!("  {v} : int init 0;")));
+
+    for (&node, &pc) in &pcs {
+        let branches = edge_probabilities(pg, node, transition_probs)
+            .into_iter()
+            .map(|(e, prob)| {
+                let mut updates = vec![format!("(pc'={})", pcs[&e.to()])];
+                match e.action() {
+                    Action::Assignment(Target::Variable(x), a) => {
+                        updates.push(format!("({x}'={})", aexpr(a)?))
+                    }
+                    Action::Assignment(Target::Array(_, _), _) => return Err(PrismError::Array),
+                    Action::Skip | Action::Condition(_) => {}
+                }
+                Ok(format!("{prob}:{}", updates.join("&")))
+            })
+            .collect::<Result<Vec<_>, PrismError>>()?;
+        if branches.is_empty() {
+            // NOTE: PRISM reports deadlocks, so terminal nodes loop forever
+            lines.push(format!("  [] pc={pc} -> 1:(pc'={pc});"));
+        } else {
+            lines.push(format!("  [] pc={pc} -> {};", branches.join(" + ")));
+        }
+    }
+    lines.push("endmodule".to_string());
+
+    Ok(lines.join("\n"))
+}
+
+/// Checks that every probability in `probs` belongs to an edge of `pg` and is
+/// in `[0, 1]`, and that the probabilities of the edges leaving each node sum
+/// to 1 once the edges without a probability have been given their share.
+pub fn verify_probabilities(
+    pg: &ProgramGraph,
+    probs: &HashMap<(Node, Node), f64>,
+) -> Result<(), ProbError> {
+    for (&(from, to), &prob) in probs {
+        if !pg.outgoing(from).iter().any(|e| e.to() == to) {
+            return Err(ProbError::UnknownEdge { from, to });
+        }
+        if !(0.0..=1.0).contains(&prob) {
+            return Err(ProbError::OutOfRange { from, to, prob });
+        }
+    }
+    for &node in pg.nodes().iter().sorted() {
+        let probs = edge_probabilities(pg, node, probs);
+        if probs.is_empty() {
+            continue;
+        }
+        let sum: f64 = probs.iter().map(|(_, p)| p).sum();
+        if (sum - 1.0).abs() > EPSILON {
+            return Err(ProbError::SumNotOne { node, sum });
+        }
+    }
+    Ok(())
+}
+
+/// The probability of taking each of the edges leaving `node`, ordered by
+/// their target. Parallel edges split the probability given for their endpoints evenly.
+fn edge_probabilities<'a>(
+    pg: &'a ProgramGraph,
+    node: Node,
+    probs: &HashMap<(Node, Node), f64>,
+) -> Vec<(&'a Edge, f64)> {
+    let outgoing = pg.outgoing(node);
+    let parallel = |e: &Edge| outgoing.iter().filter(|f| f.to() == e.to()).count() as f64;
+
+    let explicit: f64 = outgoing
+        .iter()
+        .filter_map(|e| probs.get(&(node, e.to())).map(|p| p / parallel(e)))
+        .sum();
+    let implicit = outgoing
+        .iter()
+        .filter(|e| !probs.contains_key(&(node, e.to())))
+        .count();
+    let share = if implicit == 0 {
+        0.0
+    } else {
+        (1.0 - explicit).max(0.0) / implicit as f64
+    };
+
+    outgoing
+        .iter()
+        .sorted_by_key(|e| e.to())
+        .map(|e| match probs.get(&(node, e.to())) {
+            Some(p) => (e, p / parallel(e)),
+            None => (e, share),
+        })
+        .collect()
+}
+
+fn aexpr(a: &AExpr) -> Result<String, PrismError> {
+    Ok(match a {
+        AExpr::Number(n) => n.to_string(),
+        AExpr::Reference(Target::Variable(Variable(v))) => v.clone(),
+        AExpr::Reference(Target::Array(_, _)) => return Err(PrismError::Array),
+        AExpr::Binary(l, AOp::Pow, r) => format!("pow({}, {})", aexpr(l)?, aexpr(r)?),
+        AExpr::Binary(l, op, r) => {
+            let op = match op {
+                AOp::Plus => "+",
+                AOp::Minus => "-",
+                AOp::Times => "*",
+                // NOTE: Division in PRISM is on reals, which does not agree
+                // with the integer division of GCL
+                AOp::Divide | AOp::Pow => return Err(PrismError::Unsupported(a.to_string())),
+            };
+            format!("({} {op} {})", aexpr(l)?, aexpr(r)?)
+        }
+        AExpr::Minus(x) => format!("(-{})", aexpr(x)?),
+        AExpr::Function(Function::Min(l, r)) => format!("min({}, {})", aexpr(l)?, aexpr(r)?),
+        AExpr::Function(Function::Max(l, r)) => format!("max({}, {})", aexpr(l)?, aexpr(r)?),
+        AExpr::Function(f) => return Err(PrismError::Unsupported(f.to_string())),
+        AExpr::Cast(_, _) => return Err(PrismError::Unsupported(a.to_string())),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{pg_to_prism, verify_probabilities, ProbError};
+    use crate::{
+        parse::parse_commands,
+        pg::{Determinism, Node, ProgramGraph},
+    };
+
+    #[test]
+    fn prism_module() {
+        let cmds = parse_commands("if true -> x := x + 1 [] true -> skip fi").unwrap();
+        let pg = ProgramGraph::new(Determinism::NonDeterministic, &cmds);
+        let q1: Node = "q1".parse().unwrap();
+        let q2: Node = "q2".parse().unwrap();
+        let probs = HashMap::from([((Node::Start, q2), 0.25)]);
+
+        assert_eq!(verify_probabilities(&pg, &probs), Ok(()));
+        assert_eq!(
+            pg_to_prism(&pg, &probs).unwrap(),
+            "mdp
+
+module program
+  pc : [0..3] init 0;
+  x : int init 0;
+  [] pc=0 -> 0.75:(pc'=1) + 0.25:(pc'=2);
+  [] pc=1 -> 1:(pc'=3);
+  [] pc=2 -> 1:(pc'=3)&(x'=(x + 1));
+  [] pc=3 -> 1:(pc'=3);
+endmodule"
+        );
+
+        let probs = HashMap::from([((Node::Start, q1), 0.25), ((Node::Start, q2), 0.5)]);
+        assert_eq!(
+            verify_probabilities(&pg, &probs),
+            Err(ProbError::SumNotOne {
+                node: Node::Start,
+                sum: 0.75
+            })
+        );
+    }
+}
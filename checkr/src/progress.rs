@@ -0,0 +1,235 @@
+//! Progress of evaluating implementations, reported as it happens.
+//!
+//! Every step is emitted as a [`tracing`] event with the target
+//! `checkr::progress`, which [`install_json_progress`] writes as JSON lines
+//! for other tools to follow. Embedders which do not use `tracing` can pass a
+//! [`ProgressCallback`] instead.
+
+use std::{io::Write, sync::Mutex, time::Duration};
+
+use tracing::{
+    field::{Field, Visit},
+    info, Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, prelude::*, Layer};
+
+use crate::env::{Analysis, ValidationResult};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgressEvent {
+    CompileStarted,
+    CompileFinished {
+        time: Duration,
+        success: bool,
+    },
+    SampleStarted {
+        analysis: Analysis,
+        seed: u64,
+    },
+    SampleFinished {
+        analysis: Analysis,
+        seed: u64,
+        time: Duration,
+        verdict: &'static str,
+    },
+}
+
+pub trait ProgressCallback: Sync {
+    fn on_event(&self, event: &ProgressEvent);
+}
+
+impl ProgressCallback for () {
+    fn on_event(&self, _event: &ProgressEvent) {}
+}
+
+impl<F> ProgressCallback for F
+where
+    F: Fn(&ProgressEvent) + Sync,
+{
+    fn on_event(&self, event: &ProgressEvent) {
+        self(event)
+    }
+}
+
+/// Emits `event` as a `tracing` event and passes it on to `progress`.
+pub(crate) fn emit(progress: &impl ProgressCallback, event: ProgressEvent) {
+    match &event {
+        ProgressEvent::CompileStarted => info!(event = "compile_started", "compiling"),
+        ProgressEvent::CompileFinished { time, success } => info!(
+            event = "compile_finished",
+            duration_ms = time.as_millis() as u64,
+            success,
+            "compiled"
+        ),
+        ProgressEvent::SampleStarted { analysis, seed } => info!(
+            event = "sample_started",
+            analysis = analysis.command(),
+            seed,
+            "running sample"
+        ),
+        ProgressEvent::SampleFinished {
+            analysis,
+            seed,
+            time,
+            verdict,
+        } => info!(
+            event = "sample_finished",
+            analysis = analysis.command(),
+            seed,
+            duration_ms = time.as_millis() as u64,
+            verdict,
+            "ran sample"
+        ),
+    }
+    progress.on_event(&event);
+}
+
+/// A short name for the outcome of validating a sample.
+pub(crate) fn verdict(result: &color_eyre::Result<ValidationResult>) -> &'static str {
+    match result {
+        Ok(ValidationResult::CorrectTerminated) => "CorrectTerminated",
+        Ok(ValidationResult::CorrectNonTerminated { .. }) => "CorrectNonTerminated",
+        Ok(ValidationResult::Mismatch { .. }) => "Mismatch",
+        Ok(ValidationResult::TimeOut) => "TimeOut",
+        Err(_) => "Error",
+    }
+}
+
+/// A layer writing the progress events as JSON objects, one per line, with a
+/// key for each field of the event.
+pub struct JsonProgressLayer<W> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write> JsonProgressLayer<W> {
+    pub fn new(writer: W) -> Self {
+        JsonProgressLayer {
+            writer: Mutex::new(writer),
+        }
+    }
+}
+
+impl<S: Subscriber, W: Write + Send + 'static> Layer<S> for JsonProgressLayer<W> {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().target() != module_path!() {
+            return;
+        }
+        let mut fields = JsonFields::default();
+        event.record(&mut fields);
+        let mut writer = self.writer.lock().unwrap();
+        // NOTE: Progress is best effort, so a broken writer is not an error
+        let _ = writeln!(writer, "{}", serde_json::Value::Object(fields.0));
+        let _ = writer.flush();
+    }
+}
+
+#[derive(Default)]
+struct JsonFields(serde_json::Map<String, serde_json::Value>);
+
+impl Visit for JsonFields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{value:?}").into());
+    }
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+}
+
+/// Installs a global subscriber which writes the progress events to `writer`
+/// as JSON lines. Fails if a global subscriber is already installed.
+pub fn install_json_progress<W: Write + Send + 'static>(
+    writer: W,
+) -> Result<(), tracing_subscriber::util::TryInitError> {
+    tracing_subscriber::registry()
+        .with(JsonProgressLayer::new(writer))
+        .try_init()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        sync::{Arc, Mutex},
+    };
+
+    use tracing_subscriber::prelude::*;
+
+    use super::{JsonProgressLayer, ProgressEvent};
+    use crate::{driver::Driver, env::SignEnv, tests::unique_temp_dir, ProgramGenerationBuilder};
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn two_sample_events() {
+        let buf = SharedBuf::default();
+        let _guard = tracing::subscriber::set_default(
+            tracing_subscriber::registry().with(JsonProgressLayer::new(buf.clone())),
+        );
+
+        let dir = unique_temp_dir("progress-events");
+        std::fs::write(dir.join("run.sh"), "echo 'not json'\n").unwrap();
+        let driver = Driver::new(&dir, "sh run.sh");
+
+        let events = Mutex::new(vec![]);
+        let progress = |e: &ProgressEvent| events.lock().unwrap().push(e.clone());
+        for seed in [1, 2] {
+            ProgramGenerationBuilder::new(crate::env::Analysis::Sign)
+                .seed(Some(seed))
+                .build()
+                .run_analysis_with_progress(&SignEnv, &driver, &progress)
+                .await;
+        }
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(buf.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        let sequence: Vec<_> = lines
+            .iter()
+            .map(|l| (l["event"].as_str().unwrap(), l["seed"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(
+            sequence,
+            [
+                ("sample_started", 1),
+                ("sample_finished", 1),
+                ("sample_started", 2),
+                ("sample_finished", 2),
+            ]
+        );
+        assert_eq!(lines[1]["analysis"], "sign");
+        assert_eq!(lines[1]["verdict"], "Error");
+
+        let events = events.into_inner().unwrap();
+        assert_eq!(events.len(), 4);
+        assert!(matches!(
+            events[3],
+            ProgressEvent::SampleFinished {
+                seed: 2,
+                verdict: "Error",
+                ..
+            }
+        ));
+    }
+}
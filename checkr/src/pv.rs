@@ -62,7 +62,7 @@ impl Command {
                 .map(|gc| BExpr::Not(gc.0.clone().into()))
                 .reduce(|a, b| BExpr::logic(a, LogicOp::Land, b))
                 .unwrap(),
-            Command::EnrichedLoop(i, guards) => {
+            Command::EnrichedLoop(i, _, guards) => {
                 let done = guards
                     .iter()
                     .map(|gc| BExpr::Not(gc.0.clone().into()))
@@ -74,6 +74,15 @@ impl Command {
             Command::Annotated(_, _, q) => q.clone(),
             Command::Break => todo!(),
             Command::Continue => todo!(),
+            Command::For(_, _, _, _) => self.desugar().sp(p),
+            Command::Choice(alternatives) => alternatives
+                .iter()
+                .map(|c| c.sp(p))
+                .reduce(|a, b| BExpr::logic(a, LogicOp::Lor, b))
+                .unwrap(),
+            // NOTE: Preconditions are assumed and postconditions are asserted
+            Command::Precondition(b) => BExpr::logic(p.clone(), LogicOp::Land, b.clone()),
+            Command::Postcondition(_) => p.clone(),
         }
     }
     pub fn vc(&self, r: &BExpr) -> Vec<BExpr> {
@@ -83,7 +92,7 @@ impl Command {
             Command::If(guards) => guards_vc(guards, r),
             // TODO: Could we make something more useful/obvious here?
             Command::Loop(_) => vec![],
-            Command::EnrichedLoop(i, guards) => {
+            Command::EnrichedLoop(i, variant, guards) => {
                 let mut conditions = vec![
                     BExpr::logic(r.clone(), LogicOp::Implies, i.clone()),
                     BExpr::logic(guards_sp(guards, i), LogicOp::Implies, i.clone()),
@@ -91,6 +100,10 @@ impl Command {
 
                 conditions.extend_from_slice(&guards_vc(guards, i));
 
+                if let Some(variant) = variant {
+                    conditions.extend(variant_vc(i, variant, guards));
+                }
+
                 conditions
             }
             Command::Annotated(p, c, q) => {
@@ -102,9 +115,45 @@ impl Command {
             }
             Command::Break => todo!(),
             Command::Continue => todo!(),
+            Command::For(_, _, _, _) => self.desugar().vc(r),
+            Command::Choice(alternatives) => alternatives.iter().flat_map(|c| c.vc(r)).collect(),
+            Command::Precondition(_) => vec![],
+            Command::Postcondition(b) => vec![BExpr::logic(r.clone(), LogicOp::Implies, b.clone())],
         }
     }
 }
+/// The conditions for `variant` to prove the termination of a loop with the
+/// invariant `i`: whenever a guard holds the variant is non-negative, and each
+/// iteration strictly decreases it from its initial value `v0`.
+fn variant_vc(i: &BExpr, variant: &AExpr, guards: &[Guard]) -> Vec<BExpr> {
+    let v0 = AExpr::Reference(Target::Variable(Variable(format!(
+        "_fresh_{}",
+        FRESH_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+    ))));
+
+    guards
+        .iter()
+        .flat_map(|gc| {
+            let non_negative = BExpr::logic(
+                BExpr::logic(i.clone(), LogicOp::Land, gc.0.clone()),
+                LogicOp::Implies,
+                BExpr::rel(variant.clone(), RelOp::Ge, AExpr::Number(0)),
+            );
+            let initial = BExpr::logic(
+                i.clone(),
+                LogicOp::Land,
+                BExpr::rel(variant.clone(), RelOp::Eq, v0.clone()),
+            );
+            let decreasing = BExpr::logic(
+                gc.sp(&initial),
+                LogicOp::Implies,
+                BExpr::rel(variant.clone(), RelOp::Lt, v0.clone()),
+            );
+            [non_negative, decreasing]
+        })
+        .collect()
+}
+
 fn guards_sp(guards: &[Guard], p: &BExpr) -> BExpr {
     guards
         .iter()
@@ -192,6 +241,7 @@ impl AExpr {
             AExpr::Binary(l, op, r) => AExpr::binary(l.subst_var(t, x), *op, r.subst_var(t, x)),
             AExpr::Minus(e) => AExpr::Minus(Box::new(e.subst_var(t, x))),
             AExpr::Function(f) => AExpr::Function(f.subst_var(t, x)),
+            AExpr::Cast(e, c) => AExpr::Cast(Box::new(e.subst_var(t, x)), *c),
         }
     }
 
@@ -209,6 +259,7 @@ impl AExpr {
                 _ => AExpr::Minus(Box::new(e.simplify())),
             },
             AExpr::Function(_) => self.clone(),
+            AExpr::Cast(e, c) => AExpr::Cast(Box::new(e.simplify()), c),
         }
     }
 }
@@ -247,3 +298,84 @@ impl Target<Box<AExpr>> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::{
+        ast::{AExpr, BExpr, Command, LogicOp, Quantifier},
+        interpreter::InterpreterMemory,
+        parse::parse_commands,
+        sign::Memory,
+    };
+
+    fn loop_vcs(src: &str) -> Vec<BExpr> {
+        let cmds = parse_commands(src).unwrap();
+        let [Command::Annotated(p, c, _)] = &cmds.0[..] else {
+            panic!("expected an annotated program")
+        };
+        c.vc(p)
+    }
+
+    /// Replaces quantifiers by a conjunction or disjunction over `-4..=4`.
+    fn expand_quantifiers(b: &BExpr) -> BExpr {
+        match b {
+            BExpr::Quantified(q, x, body) => {
+                let body = expand_quantifiers(body);
+                let op = match q {
+                    Quantifier::Exists => LogicOp::Lor,
+                    Quantifier::Forall => LogicOp::Land,
+                };
+                (-4..=4)
+                    .map(|n| body.subst_var(x, &AExpr::Number(n)))
+                    .reduce(|l, r| BExpr::logic(l, op, r))
+                    .unwrap()
+            }
+            BExpr::Logic(l, op, r) => {
+                BExpr::logic(expand_quantifiers(l), *op, expand_quantifiers(r))
+            }
+            BExpr::Not(b) => BExpr::Not(Box::new(expand_quantifiers(b))),
+            BExpr::Bool(_) | BExpr::Rel(_, _, _) => b.clone(),
+        }
+    }
+
+    /// Whether `vc` holds when all of its variables, including the quantified
+    /// ones, range over `-4..=4`.
+    fn holds_for_small_values(vc: &BExpr) -> bool {
+        let vc = expand_quantifiers(vc);
+        let memory: InterpreterMemory = Memory::from_targets(vc.fv(), |_| 0, |_| vec![]);
+        let count = memory.variables.len();
+        (0..count)
+            .map(|_| -4..=4)
+            .multi_cartesian_product()
+            .all(|values| {
+                let mut memory = memory.clone();
+                for (x, v) in memory.variables.values_mut().zip(values) {
+                    *x = v;
+                }
+                vc.semantics(&memory) == Ok(true)
+            })
+    }
+
+    #[test]
+    fn variants() {
+        let plain = loop_vcs("{ x >= 0 } do { x >= 0 } x > 0 -> x := x - 1 od { x = 0 }");
+        let total =
+            loop_vcs("{ x >= 0 } do [inv: x >= 0; var: x] x > 0 -> x := x - 1 od { x = 0 }");
+        assert_eq!(total.len(), plain.len() + 2);
+        assert_eq!(
+            total[plain.len()].to_string(),
+            "(((x >= 0) & (x > 0)) ==> (x >= 0))"
+        );
+        assert!(total.iter().all(holds_for_small_values));
+
+        // A constant does not decrease, which is witnessed by any memory where
+        // the guard holds
+        let constant = loop_vcs("{ true } do [inv: true; var: 1] x > 0 -> skip od { true }");
+        let decreasing = constant.last().unwrap();
+        let memory: InterpreterMemory = Memory::from_targets(decreasing.fv(), |_| 1, |_| vec![]);
+        assert_eq!(decreasing.semantics(&memory), Ok(false));
+        assert!(!holds_for_small_values(decreasing));
+    }
+}
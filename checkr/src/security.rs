@@ -1,10 +1,13 @@
-use std::{collections::HashSet, fmt::Display};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    fmt::Display,
+};
 
 use itertools::{chain, Itertools};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    ast::{Command, Commands, Guard, Target},
+    ast::{Command, Commands, Guard, Target, Variable},
     gcl,
     parse::ParseError,
     sign::Memory,
@@ -45,23 +48,31 @@ where
 
 impl Commands {
     pub fn flows(&self) -> HashSet<Flow<Target>> {
-        self.sec(&Default::default())
+        self.sec(&Default::default(), true)
+    }
+    /// The flows from the variables of guards into the targets assigned under
+    /// them, leaving out the flows from the assigned expressions.
+    pub fn implicit_flows(&self) -> HashSet<Flow<Target>> {
+        self.sec(&Default::default(), false)
     }
-    fn sec(&self, implicit: &HashSet<Target>) -> HashSet<Flow<Target>> {
-        self.0.iter().flat_map(|c| c.sec(implicit)).collect()
+    fn sec(&self, implicit: &HashSet<Target>, explicit: bool) -> HashSet<Flow<Target>> {
+        self.0
+            .iter()
+            .flat_map(|c| c.sec(implicit, explicit))
+            .collect()
     }
 }
 
 impl Command {
-    fn sec(&self, implicit: &HashSet<Target>) -> HashSet<Flow<Target>> {
+    fn sec(&self, implicit: &HashSet<Target>, explicit: bool) -> HashSet<Flow<Target>> {
         match self {
             Command::Assignment(t, a) => chain!(
                 implicit.iter().cloned(),
                 match t {
-                    Target::Variable(_) => Default::default(),
-                    Target::Array(_, idx) => idx.fv(),
+                    Target::Array(_, idx) if explicit => idx.fv(),
+                    _ => Default::default(),
                 },
-                a.fv()
+                if explicit { a.fv() } else { Default::default() }
             )
             .map(|i| Flow {
                 from: i,
@@ -69,12 +80,12 @@ impl Command {
             })
             .collect(),
             Command::Skip => HashSet::default(),
-            Command::If(c) | Command::Loop(c) | Command::EnrichedLoop(_, c) => {
+            Command::If(c) | Command::Loop(c) | Command::EnrichedLoop(_, _, c) => {
                 c.iter()
                     .fold(
                         (implicit.clone(), HashSet::default()),
                         |(implicit, flows), guard| {
-                            let (new_implicit, new_flows) = guard.sec2(&implicit);
+                            let (new_implicit, new_flows) = guard.sec2(&implicit, explicit);
 
                             (
                                 implicit.union(&new_implicit).cloned().collect(),
@@ -84,17 +95,29 @@ impl Command {
                     )
                     .1
             }
-            Command::Annotated(_, c, _) => c.sec(implicit),
+            Command::Annotated(_, c, _) => c.sec(implicit, explicit),
             Command::Break => HashSet::default(),
             Command::Continue => HashSet::default(),
+            Command::For(_, _, _, _) => self.desugar().sec(implicit, explicit),
+            // NOTE: The choice does not depend on any variable, so there are no
+            // implicit flows from it
+            Command::Choice(alternatives) => alternatives
+                .iter()
+                .flat_map(|c| c.sec(implicit, explicit))
+                .collect(),
+            Command::Precondition(_) | Command::Postcondition(_) => HashSet::default(),
         }
     }
 }
 
 impl Guard {
-    fn sec2(&self, implicit: &HashSet<Target>) -> (HashSet<Target>, HashSet<Flow<Target>>) {
+    fn sec2(
+        &self,
+        implicit: &HashSet<Target>,
+        explicit: bool,
+    ) -> (HashSet<Target>, HashSet<Flow<Target>>) {
         let implicit = implicit.iter().cloned().chain(self.0.fv()).collect();
-        let flows = self.1.sec(&implicit);
+        let flows = self.1.sec(&implicit, explicit);
         (implicit, flows)
     }
 }
@@ -116,40 +139,73 @@ impl std::fmt::Display for SecurityClass {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SecurityLattice {
     allowed: HashSet<Flow<SecurityClass>>,
+    /// The flows the lattice was declared with, before taking the closure
+    #[serde(default)]
+    declared: HashSet<Flow<SecurityClass>>,
 }
 
 impl SecurityLattice {
     pub fn new(flows: &[Flow<SecurityClass>]) -> SecurityLattice {
-        let mut allowed: HashSet<Flow<SecurityClass>> = flows.iter().cloned().collect();
-        let mut last_len = 0;
-        loop {
-            let mut to_add: HashSet<Flow<SecurityClass>> = HashSet::new();
-            for f in &allowed {
-                for a in &allowed {
-                    // a -> b, b -> c
-                    // --------------
-                    //     a -> c
-                    let new_flow = Flow {
-                        from: f.from.clone(),
-                        into: a.into.clone(),
-                    };
-                    if f.into == a.from && !allowed.contains(&new_flow) {
-                        to_add.insert(new_flow);
+        let declared: HashSet<_> = flows.iter().cloned().collect();
+        SecurityLattice {
+            allowed: declared.clone(),
+            declared,
+        }
+        .with_transitive_closure()
+    }
+    /// The lattice allowing every flow in the reflexive-transitive closure of
+    /// the flows allowed by `self`, computed with Floyd-Warshall.
+    pub fn with_transitive_closure(&self) -> SecurityLattice {
+        let classes: Vec<&SecurityClass> = self
+            .allowed
+            .iter()
+            .flat_map(|f| [&f.from, &f.into])
+            .sorted()
+            .dedup()
+            .collect();
+        let n = classes.len();
+
+        let mut reach = vec![vec![false; n]; n];
+        for (i, a) in classes.iter().enumerate() {
+            for (j, b) in classes.iter().enumerate() {
+                reach[i][j] = i == j
+                    || self.allowed.contains(&Flow {
+                        from: (*a).clone(),
+                        into: (*b).clone(),
+                    });
+            }
+        }
+        for k in 0..n {
+            let via = reach[k].clone();
+            for row in &mut reach {
+                if row[k] {
+                    for (r, v) in row.iter_mut().zip(&via) {
+                        *r |= v;
                     }
                 }
             }
+        }
 
-            for f in to_add {
-                allowed.insert(f);
-            }
+        let allowed = classes
+            .iter()
+            .enumerate()
+            .flat_map(|(i, a)| {
+                let reach = &reach;
+                classes
+                    .iter()
+                    .enumerate()
+                    .filter(move |(j, _)| reach[i][*j])
+                    .map(move |(_, b)| Flow {
+                        from: (*a).clone(),
+                        into: (*b).clone(),
+                    })
+            })
+            .collect();
 
-            if allowed.len() == last_len {
-                break;
-            }
-            last_len = allowed.len();
+        SecurityLattice {
+            allowed,
+            declared: self.declared.clone(),
         }
-
-        SecurityLattice { allowed }
     }
     pub fn parse(src: &str) -> color_eyre::Result<SecurityLattice> {
         let flows = gcl::SecurityLatticeParser::new()
@@ -162,6 +218,45 @@ impl SecurityLattice {
         f.from == f.into || self.allowed.contains(f)
     }
 
+    /// The classes which `class` is declared to flow into, or which are
+    /// declared to flow into `class` when going down.
+    fn neighbours<'a>(
+        &'a self,
+        class: &'a SecurityClass,
+        up: bool,
+    ) -> impl Iterator<Item = &'a SecurityClass> + 'a {
+        self.declared.iter().filter_map(move |f| match up {
+            true => (&f.from == class).then_some(&f.into),
+            false => (&f.into == class).then_some(&f.from),
+        })
+    }
+
+    /// Searches the declared flows breadth-first from `start`, upwards or
+    /// downwards, for the closest class other than `start` which satisfies
+    /// `pred`.
+    fn closest_class(
+        &self,
+        start: &SecurityClass,
+        up: bool,
+        pred: impl Fn(&SecurityClass) -> bool,
+    ) -> Option<SecurityClass> {
+        let mut visited: HashSet<&SecurityClass> = [start].into_iter().collect();
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(class) = queue.pop_front() {
+            for n in self.neighbours(class, up).sorted() {
+                if visited.insert(n) {
+                    if pred(n) {
+                        return Some(n.clone());
+                    }
+                    queue.push_back(n);
+                }
+            }
+        }
+
+        None
+    }
+
     fn all_allowed<'a>(
         &'a self,
         classification: &'a Memory<SecurityClass>,
@@ -198,20 +293,297 @@ impl SecurityAnalysisOutput {
         lattice: &SecurityLattice,
         cmds: &Commands,
     ) -> Self {
-        let allowed = lattice.all_allowed(mapping).sorted().dedup().collect_vec();
+        let allowed = lattice.all_allowed(mapping).collect_vec();
         let actual = cmds.flows();
         let violations = actual
             .iter()
-            .cloned()
             .filter(|flow| !allowed.contains(flow))
-            .sorted()
-            .dedup()
+            .cloned()
             .collect();
 
-        Self {
-            actual: actual.into_iter().sorted().collect(),
+        let mut output = Self {
+            actual: actual.into_iter().collect(),
             allowed,
             violations,
+        };
+        output.canonicalize();
+        output
+    }
+
+    /// Sorts the flows and removes duplicates, such that equivalent outputs
+    /// are also equal.
+    ///
+    /// Self-flows such as `x -> x` are kept: they appear in `actual` whenever
+    /// a variable is assigned an expression depending on itself, and in
+    /// `allowed` for every classified target, but never in `violations`.
+    pub fn canonicalize(&mut self) {
+        for flows in [&mut self.actual, &mut self.allowed, &mut self.violations] {
+            flows.sort();
+            flows.dedup();
         }
     }
+
+    /// Checks whether the two outputs contain the same flows, regardless of
+    /// their order and duplicates. Flows are compared by the names of their
+    /// targets, as deserialized arrays are indistinguishable from variables.
+    pub fn is_equivalent(&self, other: &Self) -> bool {
+        fn names(flows: &[Flow<Target>]) -> BTreeSet<Flow<&str>> {
+            flows.iter().map(|f| f.map(|t| t.name())).collect()
+        }
+
+        names(&self.actual) == names(&other.actual)
+            && names(&self.allowed) == names(&other.allowed)
+            && names(&self.violations) == names(&other.violations)
+    }
+}
+
+/// A change to the classification which removes a violating flow.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum SecurityFix {
+    /// Lower the class of the source of the flow to one which is allowed to
+    /// flow into the class of the sink.
+    DowngradeSource {
+        var: Variable,
+        from: SecurityClass,
+        to: SecurityClass,
+    },
+    /// Raise the class of the sink of the flow to one which the class of the
+    /// source is allowed to flow into.
+    UpgradeSink {
+        var: Variable,
+        from: SecurityClass,
+        to: SecurityClass,
+    },
+}
+
+/// Suggests classification changes which remove the violations in `result`.
+///
+/// For each violation the closest class which resolves it is found by a
+/// breadth-first search over the declared flows of the lattice, downwards
+/// from the class of the source and upwards from the class of the sink, such
+/// that the suggested changes are as small as possible. Violations involving
+/// arrays are not fixed, as an array shares its class with all of its
+/// elements.
+pub fn suggest_fixes(
+    result: &SecurityAnalysisOutput,
+    classification: &Memory<SecurityClass>,
+    lattice: &SecurityLattice,
+) -> Vec<SecurityFix> {
+    result
+        .violations
+        .iter()
+        .flat_map(|flow| {
+            let (Target::Variable(source), Target::Variable(sink)) = (&flow.from, &flow.into)
+            else {
+                return vec![];
+            };
+            let (from, into) = match (classification.get_var(source), classification.get_var(sink))
+            {
+                (Some(from), Some(into)) => (from, into),
+                _ => return vec![],
+            };
+
+            let downgrade = lattice
+                .closest_class(from, false, |c| {
+                    lattice.allows(&Flow {
+                        from: c.clone(),
+                        into: into.clone(),
+                    })
+                })
+                .map(|to| SecurityFix::DowngradeSource {
+                    var: source.clone(),
+                    from: from.clone(),
+                    to,
+                });
+            let upgrade = lattice
+                .closest_class(into, true, |c| {
+                    lattice.allows(&Flow {
+                        from: from.clone(),
+                        into: c.clone(),
+                    })
+                })
+                .map(|to| SecurityFix::UpgradeSink {
+                    var: sink.clone(),
+                    from: into.clone(),
+                    to,
+                });
+
+            chain!(downgrade, upgrade).collect_vec()
+        })
+        .sorted()
+        .dedup()
+        .collect()
+}
+
+/// A flow from a variable in a guard into a variable assigned under that
+/// guard, as in `if x > 0 -> y := 1 fi`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ImplicitFlow {
+    pub guard_var: Variable,
+    pub affected_var: Variable,
+    pub guard_class: SecurityClass,
+    pub affected_class: SecurityClass,
+}
+
+/// Finds the implicit flows of `cmds` which are violations, that is, where the
+/// class of the guard is not allowed to flow into the class of the affected
+/// variable. Variables which are not classified are ignored.
+pub fn detect_implicit_flows(
+    cmds: &Commands,
+    classification: &HashMap<Variable, SecurityClass>,
+    lattice: &SecurityLattice,
+) -> Vec<ImplicitFlow> {
+    let flows: BTreeSet<(Variable, Variable)> = cmds
+        .implicit_flows()
+        .into_iter()
+        .filter_map(|flow| match flow {
+            Flow {
+                from: Target::Variable(x),
+                into: Target::Variable(y),
+            } => Some((x, y)),
+            _ => None,
+        })
+        .collect();
+
+    flows
+        .into_iter()
+        .filter_map(|(guard_var, affected_var)| {
+            let guard_class = classification.get(&guard_var)?.clone();
+            let affected_class = classification.get(&affected_var)?.clone();
+            let allowed = lattice.allows(&Flow {
+                from: guard_class.clone(),
+                into: affected_class.clone(),
+            });
+            (!allowed).then_some(ImplicitFlow {
+                guard_var,
+                affected_var,
+                guard_class,
+                affected_class,
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn suggest_fixes_on_chain() {
+    let class = |c: &str| SecurityClass(c.to_string());
+    let var = |v: &str| Variable(v.to_string());
+    let lattice = SecurityLattice::parse("Low < Mid, Mid < High").unwrap();
+    let cmds = crate::parse::parse_commands("y := x; w := z").unwrap();
+    let classification = Memory::from_targets(
+        cmds.fv(),
+        |v| match v.0.as_str() {
+            "x" => class("High"),
+            "z" => class("Mid"),
+            _ => class("Low"),
+        },
+        |_| class("Low"),
+    );
+    let result = SecurityAnalysisOutput::run(&classification, &lattice, &cmds);
+
+    // NOTE: `Low` may flow directly into `High` after the closure, but `w` only
+    // needs to be raised to `Mid`
+    assert_eq!(
+        suggest_fixes(&result, &classification, &lattice),
+        vec![
+            SecurityFix::DowngradeSource {
+                var: var("x"),
+                from: class("High"),
+                to: class("Low"),
+            },
+            SecurityFix::DowngradeSource {
+                var: var("z"),
+                from: class("Mid"),
+                to: class("Low"),
+            },
+            SecurityFix::UpgradeSink {
+                var: var("w"),
+                from: class("Low"),
+                to: class("Mid"),
+            },
+            SecurityFix::UpgradeSink {
+                var: var("y"),
+                from: class("Low"),
+                to: class("High"),
+            },
+        ]
+    );
+}
+
+#[test]
+fn implicit_flow_violations() {
+    let class = |c: &str| SecurityClass(c.to_string());
+    let var = |v: &str| Variable(v.to_string());
+    let lattice = SecurityLattice::parse("Public < Private").unwrap();
+    let cmds = crate::parse::parse_commands(
+        "if x > 0 -> y := 1 [] z > 0 -> z := 2 fi; do y > 0 -> x := 0; y := y - 1 od",
+    )
+    .unwrap();
+    let classification = [
+        (var("x"), class("Private")),
+        (var("y"), class("Public")),
+        (var("z"), class("Public")),
+    ]
+    .into_iter()
+    .collect();
+
+    assert_eq!(
+        detect_implicit_flows(&cmds, &classification, &lattice),
+        vec![
+            ImplicitFlow {
+                guard_var: var("x"),
+                affected_var: var("y"),
+                guard_class: class("Private"),
+                affected_class: class("Public"),
+            },
+            ImplicitFlow {
+                guard_var: var("x"),
+                affected_var: var("z"),
+                guard_class: class("Private"),
+                affected_class: class("Public"),
+            },
+        ]
+    );
+}
+
+#[test]
+fn equivalent_outputs() {
+    let var = |v: &str| Target::Variable(Variable(v.to_string()));
+    let flow = |from: &str, into: &str| Flow {
+        from: var(from),
+        into: var(into),
+    };
+    let output = SecurityAnalysisOutput {
+        actual: vec![flow("x", "y"), flow("y", "y")],
+        allowed: vec![flow("x", "x"), flow("y", "y")],
+        violations: vec![flow("x", "y")],
+    };
+    let mut shuffled = SecurityAnalysisOutput {
+        actual: vec![flow("y", "y"), flow("x", "y"), flow("y", "y")],
+        allowed: vec![flow("y", "y"), flow("x", "x")],
+        violations: vec![flow("x", "y"), flow("x", "y")],
+    };
+
+    assert!(output.is_equivalent(&shuffled));
+    assert!(shuffled.is_equivalent(&output));
+    shuffled.canonicalize();
+    assert_eq!(shuffled, output);
+
+    shuffled.violations.clear();
+    assert!(!output.is_equivalent(&shuffled));
+}
+
+#[test]
+fn transitive_flows() {
+    let class = |c: &str| SecurityClass(c.to_string());
+    let flow = |from: &str, into: &str| Flow {
+        from: class(from),
+        into: class(into),
+    };
+    let lattice = SecurityLattice::new(&[flow("A", "B"), flow("B", "C")]);
+
+    assert!(lattice.allows(&flow("A", "C")));
+    assert!(lattice.allows(&flow("A", "A")));
+    assert!(!lattice.allows(&flow("C", "A")));
+    assert_eq!(lattice, lattice.with_transitive_closure());
 }
@@ -0,0 +1,148 @@
+//! Shrinking of programs, to find a small program which still shows a problem
+//! found on a large generated one.
+
+use crate::ast::{Command, Commands, Guard};
+
+/// The programs which are one step smaller than `cmds`: dropping a command,
+/// replacing a command by one of the programs nested in it, dropping a guard
+/// or an alternative, or shrinking a nested program. Larger reductions come
+/// first, such that greedily taking the first candidate which still shows the
+/// problem converges quickly.
+pub fn candidates(cmds: &Commands) -> Vec<Commands> {
+    let mut result = vec![];
+
+    if cmds.0.len() > 1 {
+        for i in 0..cmds.0.len() {
+            let mut smaller = cmds.0.clone();
+            smaller.remove(i);
+            result.push(Commands(smaller));
+        }
+    }
+    for (i, cmd) in cmds.0.iter().enumerate() {
+        for inner in nested(cmd) {
+            let mut smaller = cmds.0.clone();
+            smaller.splice(i..=i, inner.0.iter().cloned());
+            result.push(Commands(smaller));
+        }
+    }
+    for (i, cmd) in cmds.0.iter().enumerate() {
+        for replacement in shrink_command(cmd) {
+            let mut smaller = cmds.0.clone();
+            smaller[i] = replacement;
+            result.push(Commands(smaller));
+        }
+    }
+
+    result
+}
+
+/// The programs nested directly in `cmd`.
+fn nested(cmd: &Command) -> Vec<&Commands> {
+    match cmd {
+        Command::If(guards) | Command::Loop(guards) | Command::EnrichedLoop(_, _, guards) => {
+            guards.iter().map(|g| &g.1).collect()
+        }
+        Command::Annotated(_, c, _) | Command::For(_, _, _, c) => vec![c],
+        Command::Choice(alternatives) => alternatives.iter().collect(),
+        Command::Assignment(_, _)
+        | Command::Skip
+        | Command::Break
+        | Command::Continue
+        | Command::Precondition(_)
+        | Command::Postcondition(_) => vec![],
+    }
+}
+
+/// The commands of the same kind as `cmd` which are one step smaller.
+fn shrink_command(cmd: &Command) -> Vec<Command> {
+    let guards = |guards: &[Guard]| {
+        shrink_each(guards, |Guard(b, c)| {
+            candidates(c)
+                .into_iter()
+                .map(|c| Guard(b.clone(), c))
+                .collect()
+        })
+    };
+
+    match cmd {
+        Command::If(gs) => guards(gs).into_iter().map(Command::If).collect(),
+        Command::Loop(gs) => guards(gs).into_iter().map(Command::Loop).collect(),
+        Command::EnrichedLoop(p, v, gs) => guards(gs)
+            .into_iter()
+            .map(|gs| Command::EnrichedLoop(p.clone(), v.clone(), gs))
+            .collect(),
+        Command::Annotated(p, c, q) => candidates(c)
+            .into_iter()
+            .map(|c| Command::Annotated(p.clone(), c, q.clone()))
+            .collect(),
+        Command::For(i, from, to, c) => candidates(c)
+            .into_iter()
+            .map(|c| Command::For(i.clone(), from.clone(), to.clone(), c))
+            .collect(),
+        Command::Choice(alternatives) => shrink_each(alternatives, candidates)
+            .into_iter()
+            .map(Command::Choice)
+            .collect(),
+        Command::Assignment(_, _)
+        | Command::Skip
+        | Command::Break
+        | Command::Continue
+        | Command::Precondition(_)
+        | Command::Postcondition(_) => vec![],
+    }
+}
+
+/// The lists which are one step smaller than `items`, either by dropping an
+/// item, as long as one remains, or by replacing an item with one of its
+/// `shrink` candidates.
+fn shrink_each<T: Clone>(items: &[T], shrink: impl Fn(&T) -> Vec<T>) -> Vec<Vec<T>> {
+    let mut result = vec![];
+    if items.len() > 1 {
+        for i in 0..items.len() {
+            let mut smaller = items.to_vec();
+            smaller.remove(i);
+            result.push(smaller);
+        }
+    }
+    for (i, item) in items.iter().enumerate() {
+        for replacement in shrink(item) {
+            let mut smaller = items.to_vec();
+            smaller[i] = replacement;
+            result.push(smaller);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::candidates;
+    use crate::parse::parse_commands;
+
+    #[test]
+    fn shrink_steps() {
+        let cmds = parse_commands("x := 1; if y > 0 -> y := 2; z := 3 [] true -> skip fi").unwrap();
+        let smaller: Vec<String> = candidates(&cmds)
+            .iter()
+            .map(|c| {
+                c.to_string()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+
+        for expected in [
+            "x := 1",
+            "x := 1 ; y := 2 ; z := 3",
+            "x := 1 ; if true -> skip fi",
+            "x := 1 ; if (y > 0) -> z := 3 [] true -> skip fi",
+        ] {
+            assert!(
+                smaller.iter().any(|s| s == expected),
+                "{expected:?} is not among {smaller:#?}"
+            );
+        }
+        assert!(candidates(&parse_commands("skip").unwrap()).is_empty());
+    }
+}
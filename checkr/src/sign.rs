@@ -1,5 +1,5 @@
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     hash::Hash,
 };
 
@@ -8,14 +8,355 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     analysis::{Direction, MonotoneFramework},
-    ast::{AExpr, Array, BExpr, Int, Target, Variable},
+    ast::{AExpr, AOp, Array, BExpr, CastTarget, Commands, Function, Int, RelOp, Target, Variable},
     interpreter::InterpreterError,
-    pg::{Action, Edge, ProgramGraph},
+    pg::{Action, Edge, Node, ProgramGraph},
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct SignAnalysis {
     pub assignment: SignMemory,
+    /// The lengths of the arrays in the program. Arrays missing from this map
+    /// have an unknown length.
+    #[serde(default)]
+    pub lengths: BTreeMap<Array, LengthAbstraction>,
+}
+
+/// What is known about the length of an array. Since arrays keep their length
+/// throughout the execution, this is tracked once per analysis rather than in
+/// every [`SignMemory`].
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(tag = "Case")]
+pub enum LengthAbstraction {
+    Known {
+        length: u64,
+    },
+    #[default]
+    Unknown,
+}
+
+impl LengthAbstraction {
+    /// Whether indexing an array of this length with an index of the signs
+    /// `idx_signs` can be in bounds. Only empty arrays are ruled out by their
+    /// length, since implementations are not told the lengths of arrays, and
+    /// constant indices out of bounds are reported by
+    /// [`SignAnalysis::definitely_out_of_bounds`] instead.
+    pub fn admits(self, idx_signs: Signs) -> bool {
+        match self {
+            LengthAbstraction::Known { length: 0 } => false,
+            _ => idx_signs.intersects(Signs::ZERO | Signs::POSITIVE),
+        }
+    }
+}
+
+impl SignAnalysis {
+    /// The edges of `pg` which index an array of known length with a constant
+    /// which is out of bounds. No execution can take these edges.
+    pub fn definitely_out_of_bounds(&self, pg: &ProgramGraph) -> Vec<Edge> {
+        pg.edges()
+            .iter()
+            .filter(|e| !self.out_of_bounds_accesses(e).is_empty())
+            .cloned()
+            .collect()
+    }
+    /// The accesses on `e` of arrays with a known length at a constant index
+    /// outside of the array.
+    fn out_of_bounds_accesses(&self, e: &Edge) -> Vec<(Array, Int)> {
+        let mut accesses = vec![];
+        match e.action() {
+            Action::Assignment(x, a) => {
+                if let Target::Array(arr, idx) = x {
+                    accesses.push((arr, idx.as_ref()));
+                    idx.array_accesses(&mut accesses);
+                }
+                a.array_accesses(&mut accesses);
+            }
+            Action::Skip => {}
+            Action::Condition(b) => b.array_accesses(&mut accesses),
+        }
+        accesses
+            .into_iter()
+            .filter_map(|(arr, idx)| match (self.lengths.get(arr), idx) {
+                (Some(LengthAbstraction::Known { length }), AExpr::Number(n))
+                    if *n < 0 || *n as u64 >= *length =>
+                {
+                    Some((arr.clone(), *n))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// The facts computed by a sign analysis, together with the potential problems
+/// they reveal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignAnalysisReport {
+    pub per_node: HashMap<Node, HashSet<SignMemory>>,
+    pub warnings: Vec<SignWarning>,
+    /// Whether the facts describe every execution. Executions which divide by
+    /// zero get stuck, and the analysis only follows the ones that do not.
+    pub is_sound: bool,
+    /// The loop invariants of the program, checked against the facts at the
+    /// heads of their loops. Only filled in by
+    /// [`SignAnalysisReport::check_invariants`].
+    pub invariants: Vec<InvariantCheck>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvariantCheck {
+    pub node: Node,
+    pub invariant: BExpr,
+    /// Whether the invariant is true in every memory reaching `node`. An
+    /// invariant which is not verified might still hold, as the signs are an
+    /// over-approximation of the concrete memories.
+    pub verified: bool,
+}
+
+impl std::fmt::Display for InvariantCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let verdict = if self.verified {
+            "verified abstractly"
+        } else {
+            "not verified"
+        };
+        write!(
+            f,
+            "invariant `{}` in {}: {verdict}",
+            self.invariant, self.node
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SignWarning {
+    /// A division on an edge leaving `node` might have a divisor of zero
+    DivisionByZero { node: Node, expr: AExpr },
+    /// No memories reach `node`
+    UnreachableNode { node: Node },
+    /// Nothing is known about the sign of `var` at `node`
+    ImpreciseVariable { var: Variable, node: Node },
+    /// An edge leaving `node` accesses `arr` at the constant `index`, which is
+    /// outside of its known length
+    IndexOutOfBounds { node: Node, arr: Array, index: Int },
+}
+
+impl std::fmt::Display for SignWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignWarning::DivisionByZero { node, expr } => {
+                write!(f, "`{expr}` might divide by zero in {node}")
+            }
+            SignWarning::UnreachableNode { node } => write!(f, "{node} is unreachable"),
+            SignWarning::ImpreciseVariable { var, node } => {
+                write!(f, "`{var}` can have any sign in {node}")
+            }
+            SignWarning::IndexOutOfBounds { node, arr, index } => {
+                write!(f, "`{arr}[{index}]` is out of bounds in {node}")
+            }
+        }
+    }
+}
+
+impl SignAnalysis {
+    /// Collects the warnings for the `facts` computed by this analysis on
+    /// `pg`, for example by [`mono_analysis`](crate::analysis::mono_analysis).
+    pub fn report(
+        &self,
+        pg: &ProgramGraph,
+        facts: HashMap<Node, HashSet<SignMemory>>,
+    ) -> SignAnalysisReport {
+        let mut warnings = vec![];
+
+        for &node in pg.nodes().iter().sorted() {
+            let memories = facts.get(&node).filter(|mems| !mems.is_empty());
+            let Some(memories) = memories else {
+                warnings.push(SignWarning::UnreachableNode { node });
+                continue;
+            };
+
+            for e in pg.outgoing(node) {
+                for (arr, index) in self.out_of_bounds_accesses(e) {
+                    warnings.push(SignWarning::IndexOutOfBounds { node, arr, index });
+                }
+            }
+
+            let mut divisions = vec![];
+            for e in pg.outgoing(node) {
+                match e.action() {
+                    Action::Assignment(x, a) => {
+                        if let Target::Array(_, idx) = x {
+                            idx.divisions(&mut divisions);
+                        }
+                        a.divisions(&mut divisions);
+                    }
+                    Action::Skip => {}
+                    Action::Condition(b) => b.divisions(&mut divisions),
+                }
+            }
+            for (expr, divisor) in divisions.into_iter().dedup() {
+                if memories
+                    .iter()
+                    .any(|mem| divisor.semantics_sign(mem).contains(Signs::ZERO))
+                {
+                    warnings.push(SignWarning::DivisionByZero {
+                        node,
+                        expr: expr.clone(),
+                    });
+                }
+            }
+
+            let variables = memories.iter().flat_map(|mem| mem.variables.keys());
+            for var in variables.sorted().dedup() {
+                let signs: Signs = memories
+                    .iter()
+                    .filter_map(|mem| mem.get_var(var).copied())
+                    .collect();
+                if signs == Signs::ALL {
+                    warnings.push(SignWarning::ImpreciseVariable {
+                        var: var.clone(),
+                        node,
+                    });
+                }
+            }
+        }
+
+        let is_sound = !warnings
+            .iter()
+            .any(|w| matches!(w, SignWarning::DivisionByZero { .. }));
+
+        SignAnalysisReport {
+            per_node: facts,
+            warnings,
+            is_sound,
+            invariants: vec![],
+        }
+    }
+}
+
+impl SignAnalysisReport {
+    /// Checks the loop invariants of `pg` against the facts at the heads of
+    /// their loops, and stores the verdicts in
+    /// [`invariants`](SignAnalysisReport::invariants).
+    pub fn check_invariants(&mut self, pg: &ProgramGraph) {
+        self.invariants = pg
+            .annotations()
+            .into_iter()
+            .map(|(node, invariant)| {
+                let verified = invariant.is_quantifier_free()
+                    && self
+                        .per_node
+                        .get(&node)
+                        .into_iter()
+                        .flatten()
+                        .all(|mem| invariant.semantics_sign(mem) == Bools::TRUE);
+                InvariantCheck {
+                    node,
+                    invariant,
+                    verified,
+                }
+            })
+            .collect();
+    }
+}
+
+impl AExpr {
+    pub(crate) fn has_function(&self) -> bool {
+        match self {
+            AExpr::Number(_) | AExpr::Reference(Target::Variable(_)) => false,
+            AExpr::Reference(Target::Array(_, idx)) => idx.has_function(),
+            AExpr::Binary(l, _, r) => l.has_function() || r.has_function(),
+            AExpr::Minus(x) | AExpr::Cast(x, _) => x.has_function(),
+            AExpr::Function(_) => true,
+        }
+    }
+    /// Collects the divisions in the expression, together with their divisors.
+    fn divisions<'a>(&'a self, acc: &mut Vec<(&'a AExpr, &'a AExpr)>) {
+        match self {
+            AExpr::Number(_) | AExpr::Reference(Target::Variable(_)) => {}
+            AExpr::Reference(Target::Array(_, idx)) => idx.divisions(acc),
+            AExpr::Binary(l, op, r) => {
+                if let AOp::Divide = op {
+                    acc.push((self, r));
+                }
+                l.divisions(acc);
+                r.divisions(acc);
+            }
+            AExpr::Minus(x) | AExpr::Cast(x, _) => x.divisions(acc),
+            AExpr::Function(f) => {
+                if let Function::Division(_, r) = f {
+                    acc.push((self, r));
+                }
+                f.exprs().for_each(|x| x.divisions(acc));
+            }
+        }
+    }
+    fn array_accesses<'a>(&'a self, acc: &mut Vec<(&'a Array, &'a AExpr)>) {
+        match self {
+            AExpr::Number(_) | AExpr::Reference(Target::Variable(_)) => {}
+            AExpr::Reference(Target::Array(arr, idx)) => {
+                acc.push((arr, idx));
+                idx.array_accesses(acc);
+            }
+            AExpr::Binary(l, _, r) => {
+                l.array_accesses(acc);
+                r.array_accesses(acc);
+            }
+            AExpr::Minus(x) | AExpr::Cast(x, _) => x.array_accesses(acc),
+            AExpr::Function(f) => f.exprs().for_each(|x| x.array_accesses(acc)),
+        }
+    }
+}
+impl BExpr {
+    pub(crate) fn is_quantifier_free(&self) -> bool {
+        match self {
+            BExpr::Bool(_) | BExpr::Rel(_, _, _) => true,
+            BExpr::Logic(l, _, r) => l.is_quantifier_free() && r.is_quantifier_free(),
+            BExpr::Not(b) => b.is_quantifier_free(),
+            BExpr::Quantified(_, _, _) => false,
+        }
+    }
+    /// Whether the expression calls a [`Function`], which the sign semantics
+    /// does not cover.
+    pub(crate) fn has_function(&self) -> bool {
+        match self {
+            BExpr::Bool(_) => false,
+            BExpr::Rel(l, _, r) => l.has_function() || r.has_function(),
+            BExpr::Logic(l, _, r) => l.has_function() || r.has_function(),
+            BExpr::Not(b) => b.has_function(),
+            BExpr::Quantified(_, _, b) => b.has_function(),
+        }
+    }
+    fn divisions<'a>(&'a self, acc: &mut Vec<(&'a AExpr, &'a AExpr)>) {
+        match self {
+            BExpr::Bool(_) | BExpr::Quantified(_, _, _) => {}
+            BExpr::Rel(l, _, r) => {
+                l.divisions(acc);
+                r.divisions(acc);
+            }
+            BExpr::Logic(l, _, r) => {
+                l.divisions(acc);
+                r.divisions(acc);
+            }
+            BExpr::Not(x) => x.divisions(acc),
+        }
+    }
+    fn array_accesses<'a>(&'a self, acc: &mut Vec<(&'a Array, &'a AExpr)>) {
+        match self {
+            BExpr::Bool(_) | BExpr::Quantified(_, _, _) => {}
+            BExpr::Rel(l, _, r) => {
+                l.array_accesses(acc);
+                r.array_accesses(acc);
+            }
+            BExpr::Logic(l, _, r) => {
+                l.array_accesses(acc);
+                r.array_accesses(acc);
+            }
+            BExpr::Not(x) => x.array_accesses(acc),
+        }
+    }
 }
 
 #[derive(
@@ -41,12 +382,28 @@ impl std::fmt::Display for Sign {
 
 impl Sign {
     fn representative(self) -> impl Iterator<Item = Int> + Clone {
-        match self {
-            Sign::Positive => itertools::Either::Left([1, 2]),
-            Sign::Zero => itertools::Either::Right([0]),
-            Sign::Negative => itertools::Either::Left([-1, -2]),
-        }
-        .into_iter()
+        [sign_min_representative(self), sign_max_representative(self)]
+            .into_iter()
+            .dedup()
+    }
+}
+
+/// The value of sign `s` with the smallest magnitude.
+pub fn sign_min_representative(s: Sign) -> Int {
+    match s {
+        Sign::Positive => 1,
+        Sign::Zero => 0,
+        Sign::Negative => -1,
+    }
+}
+/// A value of sign `s` with a larger magnitude than
+/// [`sign_min_representative`], if the sign has more than one value. Together
+/// they cover every outcome of comparing two values with given signs.
+pub fn sign_max_representative(s: Sign) -> Int {
+    match s {
+        Sign::Positive => 2,
+        Sign::Zero => 0,
+        Sign::Negative => -2,
     }
 }
 
@@ -187,7 +544,7 @@ impl FromIterator<bool> for Bools {
     }
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Memory<T, A = T> {
     pub variables: BTreeMap<Variable, T>,
     pub arrays: BTreeMap<Array, A>,
@@ -306,6 +663,68 @@ impl<T, A> Memory<T, A> {
     }
 }
 
+/// Fluent construction of a [`Memory`], mostly useful for tests and hand
+/// written inputs.
+///
+/// ```ignore
+/// let mem: InterpreterMemory = MemoryBuilder::new()
+///     .var("x", 3)
+///     .array("A", vec![1, 2])
+///     .fill_missing_from(&cmds)
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct MemoryBuilder<T, A = T> {
+    memory: Memory<T, A>,
+}
+
+impl<T, A> Default for MemoryBuilder<T, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, A> MemoryBuilder<T, A> {
+    pub fn new() -> Self {
+        Self {
+            memory: Memory {
+                variables: BTreeMap::new(),
+                arrays: BTreeMap::new(),
+            },
+        }
+    }
+    pub fn var(mut self, var: impl Into<String>, value: T) -> Self {
+        self.memory.variables.insert(Variable(var.into()), value);
+        self
+    }
+    pub fn array(mut self, arr: impl Into<String>, value: impl Into<A>) -> Self {
+        self.memory.arrays.insert(Array(arr.into()), value.into());
+        self
+    }
+    /// Adds every free variable and array of `cmds` which has not been given a
+    /// value yet, using the default value of their type.
+    pub fn fill_missing_from(mut self, cmds: &Commands) -> Self
+    where
+        T: Default,
+        A: Default,
+    {
+        for t in cmds.fv() {
+            match t {
+                Target::Variable(var) => {
+                    self.memory.variables.entry(var).or_default();
+                }
+                Target::Array(arr, ()) => {
+                    self.memory.arrays.entry(arr).or_default();
+                }
+            }
+        }
+        self
+    }
+    pub fn build(self) -> Memory<T, A> {
+        self.memory
+    }
+}
+
 impl MonotoneFramework for SignAnalysis {
     type Domain = HashSet<SignMemory>;
 
@@ -320,7 +739,8 @@ impl MonotoneFramework for SignAnalysis {
                 .iter()
                 .flat_map(|mem| {
                     let idx_signs = idx.semantics_sign(mem);
-                    if idx_signs.intersects(Signs::ZERO | Signs::POSITIVE) {
+                    let length = self.lengths.get(arr).copied().unwrap_or_default();
+                    if length.admits(idx_signs) {
                         let array_signs: Signs = mem
                             .arrays
                             .get(arr)
@@ -389,21 +809,41 @@ where
     }
 }
 
+/// Removes the abstract states of `domain` in which `l op r` cannot hold.
+///
+/// For example, if `op` is [`RelOp::Gt`] and `l` is a variable `x` while `r`
+/// is `0`, all states where `x` is zero or negative are removed.
+pub fn refine_relation(
+    op: RelOp,
+    l: &AExpr,
+    r: &AExpr,
+    domain: &HashSet<SignMemory>,
+) -> HashSet<SignMemory> {
+    domain
+        .iter()
+        .filter(|mem| relation_semantics_sign(op, l, r, mem).contains(Bools::TRUE))
+        .cloned()
+        .collect()
+}
+
+fn relation_semantics_sign(op: RelOp, l: &AExpr, r: &AExpr, mem: &SignMemory) -> Bools {
+    let l = l.semantics_sign(mem);
+    let r = r.semantics_sign(mem);
+    let extremes = |s: Sign| [sign_min_representative(s), sign_max_representative(s)];
+    cartesian_flat_map(
+        l.iter().flat_map(extremes),
+        r.iter().flat_map(extremes),
+        |l, r| Some(op.semantic(l, r?)),
+    )
+    .flatten()
+    .collect()
+}
+
 impl BExpr {
-    fn semantics_sign(&self, mem: &SignMemory) -> Bools {
+    pub(crate) fn semantics_sign(&self, mem: &SignMemory) -> Bools {
         match self {
             BExpr::Bool(b) => [*b].into_iter().collect(),
-            BExpr::Rel(l, op, r) => {
-                let l = l.semantics_sign(mem);
-                let r = r.semantics_sign(mem);
-                cartesian_flat_map(
-                    l.iter().flat_map(|s| s.representative()),
-                    r.iter().flat_map(|s| s.representative()),
-                    |l, r| Some(op.semantic(l, r?)),
-                )
-                .flatten()
-                .collect()
-            }
+            BExpr::Rel(l, op, r) => relation_semantics_sign(*op, l, r, mem),
             BExpr::Logic(l, op, r) => {
                 let l = l.semantics_sign(mem);
                 let r = r.semantics_sign(mem);
@@ -421,7 +861,7 @@ impl BExpr {
     }
 }
 
-fn sign_of(n: Int) -> Sign {
+pub(crate) fn sign_of(n: Int) -> Sign {
     match n {
         _ if n > 0 => Sign::Positive,
         _ if n < 0 => Sign::Negative,
@@ -441,6 +881,26 @@ impl std::ops::Neg for Sign {
     }
 }
 
+impl CastTarget {
+    fn semantics_sign(self, s: Sign) -> Signs {
+        match (self, s) {
+            // Truncation preserves zero for every target
+            (_, Sign::Zero) => Signs::ZERO,
+            // Truncating to 32 bits keeps only the lower bits, which can
+            // represent a value of any sign
+            (CastTarget::I32, Sign::Positive | Sign::Negative) => Signs::ALL,
+            // Unsigned targets are never negative, but the lower bits might
+            // all be zero
+            (CastTarget::U32, Sign::Positive | Sign::Negative) => Signs::ZERO | Signs::POSITIVE,
+            // Positive values fit in 64 bits and are left unchanged
+            (CastTarget::U64, Sign::Positive) => Signs::POSITIVE,
+            // Negative values wrap around to values which are too large for
+            // an `Int`, which is an overflow in the interpreter
+            (CastTarget::U64, Sign::Negative) => Signs::NONE,
+        }
+    }
+}
+
 impl AExpr {
     fn semantics_sign(&self, mem: &SignMemory) -> Signs {
         match self {
@@ -491,6 +951,264 @@ impl AExpr {
             }
             AExpr::Minus(n) => n.semantics_sign(mem).map(|x| -x),
             AExpr::Function(_) => todo!("sign of a function"),
+            AExpr::Cast(x, t) => x
+                .semantics_sign(mem)
+                .iter()
+                .fold(Signs::NONE, |acc, s| acc | t.semantics_sign(s)),
         }
     }
 }
+
+#[test]
+fn abstract_invariants() {
+    use crate::{analysis::FiFo, pg::Determinism};
+
+    let cmds = crate::parse::parse_commands(
+        "{ true } x := 1; do { x > 0 } x < 10 -> x := x + 1 od; \
+         do { y > 0 } y < 10 -> y := y + 1 od { true }",
+    )
+    .unwrap();
+    let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+    let analysis = SignAnalysis {
+        assignment: Memory::from_targets(cmds.fv(), |_| Sign::Zero, |_| Signs::ZERO),
+        lengths: Default::default(),
+    };
+    let facts = crate::analysis::mono_analysis::<_, FiFo>(analysis.clone(), &pg).facts;
+    let mut report = analysis.report(&pg, facts);
+    assert!(report.invariants.is_empty());
+    report.check_invariants(&pg);
+
+    let verdicts = report
+        .invariants
+        .iter()
+        .map(|check| (check.invariant.to_string(), check.verified))
+        .collect_vec();
+    assert_eq!(
+        verdicts,
+        [
+            ("(x > 0)".to_string(), true),
+            ("(y > 0)".to_string(), false)
+        ]
+    );
+}
+
+#[test]
+fn cast_signs() {
+    let mem: SignMemory = MemoryBuilder::new().var("x", Sign::Positive).build();
+    let x = || {
+        Box::new(AExpr::Reference(Target::Variable(Variable(
+            "x".to_string(),
+        ))))
+    };
+    let signs = |e: AExpr| e.semantics_sign(&mem);
+    assert_eq!(signs(AExpr::Cast(x(), CastTarget::I32)), Signs::ALL);
+    assert_eq!(
+        signs(AExpr::Cast(x(), CastTarget::U32)),
+        Signs::ZERO | Signs::POSITIVE
+    );
+    assert_eq!(signs(AExpr::Cast(x(), CastTarget::U64)), Signs::POSITIVE);
+    assert_eq!(
+        signs(AExpr::Cast(Box::new(AExpr::Minus(x())), CastTarget::U64)),
+        Signs::NONE
+    );
+}
+
+#[test]
+fn relations_with_single_signs() {
+    use crate::parse::parse_bexpr;
+
+    let cases = [
+        ("x > 0", [Bools::TRUE, Bools::FALSE, Bools::FALSE]),
+        ("x = 0", [Bools::FALSE, Bools::TRUE, Bools::FALSE]),
+        ("x < 0", [Bools::FALSE, Bools::FALSE, Bools::TRUE]),
+    ];
+    for (src, expected) in cases {
+        let b = parse_bexpr(src).unwrap();
+        for (sign, expected) in [Sign::Positive, Sign::Zero, Sign::Negative]
+            .into_iter()
+            .zip(expected)
+        {
+            let mem = MemoryBuilder::new().var("x", sign).build();
+            assert_eq!(b.semantics_sign(&mem), expected, "{src} with x = {sign}");
+        }
+    }
+}
+
+#[test]
+fn relations_between_signs() {
+    use crate::parse::parse_bexpr;
+
+    let b = parse_bexpr("x > y").unwrap();
+    let mem = |x, y| MemoryBuilder::new().var("x", x).var("y", y).build();
+
+    assert_eq!(
+        b.semantics_sign(&mem(Sign::Positive, Sign::Zero)),
+        Bools::TRUE
+    );
+    assert_eq!(
+        b.semantics_sign(&mem(Sign::Zero, Sign::Positive)),
+        Bools::FALSE
+    );
+    assert_eq!(
+        b.semantics_sign(&mem(Sign::Positive, Sign::Positive)),
+        Bools::ALL
+    );
+    assert_eq!(
+        b.semantics_sign(&mem(Sign::Negative, Sign::Negative)),
+        Bools::ALL
+    );
+    assert_eq!(b.semantics_sign(&mem(Sign::Zero, Sign::Zero)), Bools::FALSE);
+    assert_eq!(sign_max_representative(Sign::Negative), -2);
+}
+
+#[test]
+fn refine_relations() {
+    let x = || AExpr::Reference(Target::Variable(Variable("x".to_string())));
+    let y = || AExpr::Reference(Target::Variable(Variable("y".to_string())));
+    let domain: HashSet<SignMemory> = Signs::ALL
+        .iter()
+        .cartesian_product(Signs::ALL.iter())
+        .map(|(sx, sy)| MemoryBuilder::new().var("x", sx).var("y", sy).build())
+        .collect();
+    let signs_of_x = |op| -> Signs {
+        refine_relation(op, &x(), &AExpr::Number(0), &domain)
+            .iter()
+            .map(|mem| mem.variables[&Variable("x".to_string())])
+            .collect()
+    };
+
+    assert_eq!(signs_of_x(RelOp::Eq), Signs::ZERO);
+    assert_eq!(signs_of_x(RelOp::Ne), Signs::POSITIVE | Signs::NEGATIVE);
+    assert_eq!(signs_of_x(RelOp::Gt), Signs::POSITIVE);
+    assert_eq!(signs_of_x(RelOp::Ge), Signs::POSITIVE | Signs::ZERO);
+    assert_eq!(signs_of_x(RelOp::Lt), Signs::NEGATIVE);
+    assert_eq!(signs_of_x(RelOp::Le), Signs::NEGATIVE | Signs::ZERO);
+
+    // `x < y` rules out the states where `x` is positive and `y` is not, and
+    // where `x` is zero and `y` is not positive
+    assert_eq!(refine_relation(RelOp::Lt, &x(), &y(), &domain).len(), 9 - 4);
+    assert_eq!(refine_relation(RelOp::Eq, &x(), &y(), &domain).len(), 3);
+
+    // Only the states where `x` is positive can have `x = 5`, whatever `y` is
+    let five = refine_relation(RelOp::Eq, &AExpr::Number(5), &x(), &domain);
+    assert_eq!(five.len(), 3);
+    assert!(five
+        .iter()
+        .all(|mem| mem.variables[&Variable("x".to_string())] == Sign::Positive));
+}
+
+#[test]
+fn nested_array_indexing() {
+    use crate::{
+        analysis::{mono_analysis, FiFo},
+        ast::Command,
+        parse::parse_commands,
+        pg::{Determinism, Node, ProgramGraph},
+    };
+
+    let read = |b: Signs, i: Sign| {
+        let mem: SignMemory = MemoryBuilder::new()
+            .var("i", i)
+            .array("A", Signs::POSITIVE)
+            .array("B", b)
+            .build();
+        let cmds = parse_commands("x := A[B[i]]").unwrap();
+        let Command::Assignment(_, expr) = &cmds.0[0] else {
+            unreachable!()
+        };
+        expr.semantics_sign(&mem)
+    };
+
+    // The index into `A` is the sign of `B[i]`, which may be a valid index
+    assert_eq!(read(Signs::ZERO, Sign::Zero), Signs::POSITIVE);
+    assert_eq!(read(Signs::POSITIVE, Sign::Positive), Signs::POSITIVE);
+    assert_eq!(read(Signs::ALL, Sign::Zero), Signs::POSITIVE);
+    // A negative index is always out of bounds, so nothing can be read
+    assert_eq!(read(Signs::NEGATIVE, Sign::Zero), Signs::NONE);
+    assert_eq!(read(Signs::ZERO, Sign::Negative), Signs::NONE);
+    // `B` is empty, so the inner access is always out of bounds
+    assert_eq!(read(Signs::NONE, Sign::Zero), Signs::NONE);
+
+    let cmds = parse_commands("x := A[B[0]]; A[B[x]] := -1").unwrap();
+    let pg = ProgramGraph::new(Determinism::Deterministic, &cmds);
+    let assignment = MemoryBuilder::new()
+        .var("x", Sign::Zero)
+        .array("A", Signs::POSITIVE)
+        .array("B", Signs::ZERO)
+        .build();
+    let facts = mono_analysis::<_, FiFo>(
+        SignAnalysis {
+            assignment,
+            lengths: Default::default(),
+        },
+        &pg,
+    )
+    .facts;
+    let end_signs: HashSet<(Sign, Signs)> = facts[&Node::End]
+        .iter()
+        .map(|mem| {
+            (
+                mem.variables[&Variable("x".to_string())],
+                mem.arrays[&Array("A".to_string())],
+            )
+        })
+        .collect();
+    assert_eq!(
+        end_signs,
+        [
+            (Sign::Positive, Signs::NEGATIVE),
+            (Sign::Positive, Signs::POSITIVE | Signs::NEGATIVE),
+        ]
+        .into_iter()
+        .collect()
+    );
+}
+
+#[test]
+fn sign_report() {
+    use crate::{analysis::FiFo, pg::Determinism};
+
+    let cmds = crate::parse::parse_commands(
+        "if x > 0 -> y := 2 / x [] x <= 0 -> y := 1 / x; z := y - 1 fi",
+    )
+    .unwrap();
+    let pg = ProgramGraph::new(Determinism::NonDeterministic, &cmds);
+    let analysis = SignAnalysis {
+        assignment: Memory::from_targets(cmds.fv(), |_| Sign::Zero, |_| Signs::ZERO),
+        lengths: Default::default(),
+    };
+    let facts = crate::analysis::mono_analysis::<_, FiFo>(analysis.clone(), &pg).facts;
+    let report = analysis.report(&pg, facts);
+
+    // x is zero, so only the second branch is taken, where the division gets
+    // stuck
+    let branch = |action: &str| {
+        pg.edges()
+            .iter()
+            .find(|e| e.action().to_string() == action)
+            .unwrap()
+            .from()
+    };
+    let divide = |n: Int| {
+        AExpr::binary(
+            AExpr::Number(n),
+            AOp::Divide,
+            AExpr::Reference(Target::Variable(Variable("x".to_string()))),
+        )
+    };
+    assert!(report.warnings.contains(&SignWarning::UnreachableNode {
+        node: branch("y := (2 / x)")
+    }));
+    assert!(report.warnings.contains(&SignWarning::DivisionByZero {
+        node: branch("y := (1 / x)"),
+        expr: divide(1),
+    }));
+    assert!(!report.warnings.contains(&SignWarning::DivisionByZero {
+        node: branch("y := (2 / x)"),
+        expr: divide(2),
+    }));
+    assert!(report
+        .warnings
+        .contains(&SignWarning::UnreachableNode { node: Node::End }));
+    assert!(!report.is_sound);
+}
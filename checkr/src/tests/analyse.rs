@@ -0,0 +1,36 @@
+use rand::{rngs::SmallRng, SeedableRng};
+
+use crate::{analyse, env::Analysis, parse::parse_commands, AnalyseError};
+
+const PROGRAM: &str = "x := 1; if x > 0 -> y := x [] x <= 0 -> y := -x fi";
+
+#[test]
+fn analyse_every_analysis() {
+    let cmds = parse_commands(PROGRAM).unwrap();
+    for &analysis in Analysis::ALL {
+        let input = analysis
+            .gen_input(&cmds, &mut SmallRng::seed_from_u64(0))
+            .to_string();
+        let output = analyse(PROGRAM, analysis, &input, None).unwrap();
+        assert_eq!(output.analysis(), analysis);
+
+        let err = analyse(PROGRAM, analysis, "[", None).unwrap_err();
+        assert!(matches!(err, AnalyseError::Env(_)), "{analysis}: {err}");
+    }
+}
+
+#[test]
+fn analyse_rejects_programs() {
+    assert!(matches!(
+        analyse("x := ", Analysis::Parse, "{}", None),
+        Err(AnalyseError::Parse(_))
+    ));
+    assert!(analyse("do true -> break od", Analysis::Parse, "{}", None).is_ok());
+    assert!(matches!(
+        analyse("do true -> break od", Analysis::Sign, "{}", None),
+        Err(AnalyseError::Unsupported {
+            analysis: Analysis::Sign,
+            construct: "break"
+        })
+    ));
+}
@@ -0,0 +1,21 @@
+//! The corpus of the benchmarks should stay the same between runs.
+
+use crate::{bench_programs, parse::parse_commands};
+
+use super::round_trip::normalize;
+
+#[test]
+fn bench_programs_are_stable() {
+    let programs = bench_programs();
+    let (_, generated) = &programs[0];
+    assert!(generated.0.len() >= 1000);
+    assert_eq!(bench_programs(), programs);
+
+    for (name, cmds) in programs {
+        assert_eq!(
+            parse_commands(&cmds.to_string()).unwrap(),
+            normalize(&cmds),
+            "{name} changed"
+        );
+    }
+}
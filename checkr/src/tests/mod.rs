@@ -0,0 +1,26 @@
+mod analyse;
+mod bench;
+mod round_trip;
+
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Creates an empty directory for the fixtures of a test, which is unique to
+/// the call, such that tests running concurrently, including in other
+/// processes, never share their files.
+pub(crate) fn unique_temp_dir(name: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = std::env::temp_dir().join(format!(
+        "checkr-{name}-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    // NOTE: A directory with the same name is left over from an earlier
+    // process which had the same id
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,145 @@
+//! Printing a program and parsing it again should give back the same program.
+//!
+//! Negative literals are printed as `-n`, which parses as the negation of `n`,
+//! so generated programs are compared to their reparsed version after
+//! [normalizing](normalize) such literals.
+
+use rand::{rngs::SmallRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{
+    ast::{AExpr, BExpr, CastTarget, Command, Commands, Function, Guard, Target, Variable},
+    generation::{Context, Generate},
+    parse::parse_commands,
+};
+
+/// Rewrites the negative literals `Number(-n)` of `cmds` into `Minus(Number(n))`,
+/// which is how the parser reads them.
+pub(super) fn normalize(cmds: &Commands) -> Commands {
+    Commands(cmds.0.iter().map(normalize_cmd).collect())
+}
+
+fn normalize_cmd(cmd: &Command) -> Command {
+    let guards = |guards: &[Guard]| -> Vec<Guard> {
+        guards
+            .iter()
+            .map(|Guard(b, c)| Guard(normalize_bexpr(b), normalize(c)))
+            .collect()
+    };
+    match cmd {
+        Command::Assignment(x, a) => Command::Assignment(normalize_target(x), normalize_aexpr(a)),
+        Command::Skip | Command::Break | Command::Continue => cmd.clone(),
+        Command::If(gs) => Command::If(guards(gs)),
+        Command::Loop(gs) => Command::Loop(guards(gs)),
+        Command::EnrichedLoop(p, v, gs) => Command::EnrichedLoop(
+            normalize_bexpr(p),
+            v.as_ref().map(normalize_aexpr),
+            guards(gs),
+        ),
+        Command::Annotated(p, c, q) => {
+            Command::Annotated(normalize_bexpr(p), normalize(c), normalize_bexpr(q))
+        }
+        Command::For(i, from, to, c) => Command::For(
+            i.clone(),
+            normalize_aexpr(from),
+            normalize_aexpr(to),
+            normalize(c),
+        ),
+        Command::Choice(alternatives) => {
+            Command::Choice(alternatives.iter().map(normalize).collect())
+        }
+        Command::Precondition(b) => Command::Precondition(normalize_bexpr(b)),
+        Command::Postcondition(b) => Command::Postcondition(normalize_bexpr(b)),
+    }
+}
+
+fn normalize_target(x: &Target<Box<AExpr>>) -> Target<Box<AExpr>> {
+    x.clone().map_idx(|idx| Box::new(normalize_aexpr(&idx)))
+}
+
+fn normalize_aexpr(a: &AExpr) -> AExpr {
+    let boxed = |a: &AExpr| Box::new(normalize_aexpr(a));
+    match a {
+        AExpr::Number(n) if *n < 0 => match n.checked_neg() {
+            Some(n) => AExpr::Minus(Box::new(AExpr::Number(n))),
+            None => a.clone(),
+        },
+        AExpr::Number(_) => a.clone(),
+        AExpr::Reference(x) => AExpr::Reference(normalize_target(x)),
+        AExpr::Binary(l, op, r) => AExpr::Binary(boxed(l), *op, boxed(r)),
+        AExpr::Minus(x) => AExpr::Minus(boxed(x)),
+        AExpr::Cast(x, t) => AExpr::Cast(boxed(x), *t),
+        AExpr::Function(f) => AExpr::Function(match f {
+            Function::Division(l, r) => Function::Division(boxed(l), boxed(r)),
+            Function::Min(l, r) => Function::Min(boxed(l), boxed(r)),
+            Function::Max(l, r) => Function::Max(boxed(l), boxed(r)),
+            Function::Count(arr, x) => Function::Count(arr.clone(), boxed(x)),
+            Function::LogicalCount(arr, x) => Function::LogicalCount(arr.clone(), boxed(x)),
+            Function::Length(_) | Function::LogicalLength(_) => f.clone(),
+            Function::Fac(x) => Function::Fac(boxed(x)),
+            Function::Fib(x) => Function::Fib(boxed(x)),
+        }),
+    }
+}
+
+fn normalize_bexpr(b: &BExpr) -> BExpr {
+    match b {
+        BExpr::Bool(_) => b.clone(),
+        BExpr::Rel(l, op, r) => BExpr::Rel(normalize_aexpr(l), *op, normalize_aexpr(r)),
+        BExpr::Logic(l, op, r) => BExpr::logic(normalize_bexpr(l), *op, normalize_bexpr(r)),
+        BExpr::Not(x) => BExpr::Not(Box::new(normalize_bexpr(x))),
+        BExpr::Quantified(q, x, b) => {
+            BExpr::Quantified(*q, x.clone(), Box::new(normalize_bexpr(b)))
+        }
+    }
+}
+
+/// A command using one of the extensions which the generator does not produce,
+/// built from generated parts.
+fn extension<R: Rng>(cx: &mut Context, rng: &mut R) -> Command {
+    match rng.gen_range(0..5) {
+        0 => Command::Choice(vec![
+            Commands(cx.many(1, 3, rng)),
+            Commands(cx.many(1, 3, rng)),
+        ]),
+        1 => Command::For(
+            Variable("i".to_string()),
+            AExpr::gen(cx, rng),
+            AExpr::gen(cx, rng),
+            Commands(cx.many(1, 3, rng)),
+        ),
+        2 => Command::Assignment(
+            Target::gen(cx, rng),
+            AExpr::Cast(
+                Box::new(AExpr::gen(cx, rng)),
+                *[CastTarget::I32, CastTarget::U32, CastTarget::U64]
+                    .choose(rng)
+                    .unwrap(),
+            ),
+        ),
+        3 => Command::Precondition(BExpr::gen(cx, rng)),
+        _ => Command::Postcondition(BExpr::gen(cx, rng)),
+    }
+}
+
+#[test]
+fn print_parse_round_trip() {
+    for seed in 0..1000 {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let fuel = seed as u32 % 5 + 1;
+        let mut cx = Context::new(fuel, &mut rng);
+        let mut cmds = Commands(cx.many(1, 5, &mut rng));
+        let position = rng.gen_range(0..=cmds.0.len());
+        let ext = extension(&mut cx, &mut rng);
+        cmds.0.insert(position, ext);
+
+        let src = cmds.to_string();
+        let parsed = parse_commands(&src)
+            .unwrap_or_else(|err| panic!("failed to parse program {seed}:\n{src}\n{err}"));
+        assert_eq!(
+            parsed.to_string(),
+            src,
+            "program {seed} printed differently"
+        );
+        assert_eq!(parsed, normalize(&cmds), "program {seed} changed:\n{src}");
+    }
+}
@@ -0,0 +1,124 @@
+//! Detection of variables which might be read before they are assigned.
+//!
+//! The interpreter starts every variable at the value given in its input, or
+//! zero when generating programs, so reading a variable before assigning it is
+//! never an error at runtime. It is however a common mistake, which this
+//! forward may-analysis points out.
+//!
+//! Arrays are not tracked, since assigning a single element leaves the rest of
+//! the array as it was.
+
+use std::collections::HashSet;
+
+use crate::{
+    analysis::{mono_analysis, Direction, FiFo, MonotoneFramework},
+    ast::{Commands, Target},
+    interpreter::InterpreterMemory,
+    pdg::uses,
+    pg::{Action, Determinism, Edge, ProgramGraph},
+};
+
+/// A read of `target` on `edge`, which might happen before it is assigned.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UninitReport {
+    pub edge: Edge,
+    pub target: Target,
+}
+
+impl std::fmt::Display for UninitReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` might be read before it is assigned in `{}` from {} to {}",
+            self.target,
+            self.edge.action(),
+            self.edge.from(),
+            self.edge.to()
+        )
+    }
+}
+
+/// The variables which might not have been assigned yet.
+struct MaybeUninitialized;
+
+impl MonotoneFramework for MaybeUninitialized {
+    type Domain = HashSet<Target>;
+
+    fn semantic(&self, _pg: &ProgramGraph, e: &Edge, prev: &Self::Domain) -> Self::Domain {
+        match e.action() {
+            Action::Assignment(x @ Target::Variable(_), _) => {
+                let x = x.clone().unit();
+                prev.iter().filter(|t| **t != x).cloned().collect()
+            }
+            // NOTE: Conditions which are false regardless of the memory can
+            // never be taken, so nothing flows past them
+            Action::Condition(b) if b.semantics(&InterpreterMemory::default()) == Ok(false) => {
+                Default::default()
+            }
+            Action::Assignment(Target::Array(_, _), _) | Action::Skip | Action::Condition(_) => {
+                prev.clone()
+            }
+        }
+    }
+
+    fn direction() -> Direction {
+        Direction::Forward
+    }
+
+    fn initial(&self, pg: &ProgramGraph) -> Self::Domain {
+        pg.fv()
+            .into_iter()
+            .filter(|t| matches!(t, Target::Variable(_)))
+            .collect()
+    }
+}
+
+/// The reads in `cmds` of variables which might not have been assigned on
+/// some path leading to them, in the order of their edges.
+pub fn uninitialized_reads(cmds: &Commands) -> Vec<UninitReport> {
+    let pg = ProgramGraph::new(Determinism::NonDeterministic, cmds);
+    let facts = mono_analysis::<_, FiFo>(MaybeUninitialized, &pg).facts;
+
+    let mut reports: Vec<UninitReport> = pg
+        .edges()
+        .iter()
+        .flat_map(|e| {
+            uses(e)
+                .into_iter()
+                .filter(|t| facts[&e.from()].contains(t))
+                .map(|target| UninitReport {
+                    edge: e.clone(),
+                    target,
+                })
+        })
+        .collect();
+    reports.sort();
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::uninitialized_reads;
+    use crate::parse::parse_commands;
+
+    fn reads(src: &str) -> Vec<String> {
+        uninitialized_reads(&parse_commands(src).unwrap())
+            .into_iter()
+            .map(|r| format!("{} in {}", r.target, r.edge.action()))
+            .collect()
+    }
+
+    #[test]
+    fn reads_before_writes() {
+        assert_eq!(reads("y := x + 1; x := 2"), ["x in y := (x + 1)"]);
+        assert_eq!(reads("x := 2; y := x + 1"), Vec::<String>::new());
+        assert_eq!(
+            reads("if true -> x := 1 [] true -> skip fi; y := x"),
+            ["x in y := x"]
+        );
+        assert_eq!(
+            reads("x := 0; if false -> y := z fi; if true -> skip fi"),
+            Vec::<String>::new()
+        );
+    }
+}
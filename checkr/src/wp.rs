@@ -0,0 +1,390 @@
+//! Helpers for working with loop invariants in the weakest precondition
+//! calculus.
+
+use std::collections::HashSet;
+
+use itertools::Itertools;
+
+use crate::{
+    analysis::{mono_analysis, FiFo},
+    ast::{AExpr, BExpr, Command, Commands, Guard, LogicOp, RelOp, Target, Variable},
+    interpreter::InterpreterMemory,
+    pg::{Action, Determinism, Node, ProgramGraph},
+    sign::{sign_of, Bools, Sign, SignAnalysis, SignMemory, Signs},
+};
+
+/// The most variables for which [`guess_invariant`] enumerates every
+/// combination of signs satisfying the precondition.
+const MAX_SIGN_VARIABLES: usize = 6;
+
+/// Proposes candidate invariants for the loop `do loop_guard -> loop_body od`
+/// when it is entered in a memory satisfying `pre`. The candidates are listed
+/// in increasing strength, such that each is implied by the ones after it:
+///
+/// 1. the signs the variables have at the loop head according to a sign
+///    analysis starting from every sign memory satisfying `pre`, such as
+///    `x > 0` if `x` is always positive, which holds for the initial memory
+///    and is thus implied by `pre`,
+/// 2. the precondition itself,
+/// 3. the precondition conjoined with the weakest liberal precondition of the
+///    body with respect to `pre`,
+/// 4. the precondition conjoined with the negation of the guard.
+///
+/// The last two both strengthen `pre`, but are not compared with each other.
+/// The sign candidate is left out if the loop uses arrays or functions, `pre`
+/// has quantifiers or functions, or there are too many variables, and the
+/// weakest liberal precondition is left out if the body contains loops or
+/// array assignments. Duplicate candidates are only included once.
+pub fn guess_invariant(loop_guard: &BExpr, loop_body: &Commands, pre: &BExpr) -> Vec<BExpr> {
+    let signs = sign_invariant(loop_guard, loop_body, pre);
+    let wlp = wlp(loop_body, pre).map(|wlp| BExpr::logic(pre.clone(), LogicOp::And, wlp));
+    let exit = BExpr::logic(
+        pre.clone(),
+        LogicOp::And,
+        BExpr::Not(Box::new(loop_guard.clone())),
+    );
+
+    signs
+        .into_iter()
+        .chain([pre.clone()])
+        .chain(wlp)
+        .chain([exit])
+        .unique()
+        .collect()
+}
+
+/// The conjunction of the sign constraints at the head of the loop, when
+/// starting from every sign memory which might satisfy `pre`.
+fn sign_invariant(loop_guard: &BExpr, loop_body: &Commands, pre: &BExpr) -> Option<BExpr> {
+    let cmds = Commands(vec![Command::Loop(vec![Guard(
+        loop_guard.clone(),
+        loop_body.clone(),
+    )])]);
+    let vars = cmds
+        .fv()
+        .union(&pre.fv())
+        .map(|t| match t {
+            Target::Variable(var) => Some(var.clone()),
+            Target::Array(_, ()) => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if !pre.is_quantifier_free() || pre.has_function() || vars.len() > MAX_SIGN_VARIABLES {
+        return None;
+    }
+
+    let pg = ProgramGraph::new(Determinism::NonDeterministic, &cmds);
+    let uses_function = pg.edges().iter().any(|e| match e.action() {
+        Action::Assignment(Target::Variable(_), a) => a.has_function(),
+        Action::Assignment(Target::Array(_, idx), a) => idx.has_function() || a.has_function(),
+        Action::Condition(b) => b.has_function(),
+        Action::Skip => false,
+    });
+    if uses_function {
+        return None;
+    }
+    let mut head: HashSet<SignMemory> = HashSet::new();
+    for signs in vars
+        .iter()
+        .map(|_| [Sign::Positive, Sign::Zero, Sign::Negative])
+        .multi_cartesian_product()
+    {
+        let assignment = SignMemory {
+            variables: vars.iter().cloned().zip(signs).collect(),
+            arrays: Default::default(),
+        };
+        if !pre.semantics_sign(&assignment).contains(Bools::TRUE) {
+            continue;
+        }
+        let facts = mono_analysis::<_, FiFo>(
+            SignAnalysis {
+                assignment,
+                lengths: Default::default(),
+            },
+            &pg,
+        )
+        .facts;
+        head.extend(facts[&Node::Start].iter().cloned());
+    }
+
+    vars.iter()
+        .sorted()
+        .filter_map(|var| {
+            let signs: Signs = head
+                .iter()
+                .filter_map(|mem| mem.get_var(var).copied())
+                .collect();
+            sign_constraint(var, signs)
+        })
+        .reduce(|acc, c| BExpr::logic(acc, LogicOp::And, c))
+}
+
+/// The weakest liberal precondition of `cmds` with respect to `q`, or `None`
+/// for commands other than variable assignments, `skip` and `if`.
+fn wlp(cmds: &Commands, q: &BExpr) -> Option<BExpr> {
+    cmds.0.iter().rev().try_fold(q.clone(), |q, cmd| match cmd {
+        Command::Assignment(x @ Target::Variable(_), a) => Some(q.subst_var(x, a)),
+        Command::Skip | Command::Precondition(_) | Command::Postcondition(_) => Some(q),
+        // NOTE: When no guard holds the `if` aborts, which satisfies any
+        // postcondition in the partial correctness sense
+        Command::If(guards) => guards
+            .iter()
+            .map(|Guard(b, body)| Some(BExpr::logic(b.clone(), LogicOp::Implies, wlp(body, &q)?)))
+            .reduce(|l, r| Some(BExpr::logic(l?, LogicOp::And, r?)))
+            .flatten(),
+        _ => None,
+    })
+}
+
+/// The most iterations of the loop body [`strengthen_invariant`] explores
+/// before giving up on finding an inductive strengthening.
+const MAX_STRENGTHEN_ROUNDS: usize = 8;
+
+/// Suggests a stronger invariant for the loop `do loop_guard -> loop_body od`
+/// given a `counterexample` memory for which `current_invariant` is too weak.
+///
+/// The values of the counterexample are first added as conjuncts, such as
+/// `x = 3`, which are then generalized to signs. Starting from the signs of the
+/// counterexample, the sign memories reachable by running the body while the
+/// guard might hold are added one iteration at a time, until no new ones are
+/// found. The signs of each variable in those memories then give a conjunct,
+/// such as `x >= 0` if `x` is never negative, which replaces its value.
+///
+/// If no fixed point is found within [`MAX_STRENGTHEN_ROUNDS`] iterations, or
+/// the counterexample does not assign all of the free variables of the loop,
+/// the current invariant conjoined with the values of the counterexample is
+/// returned, since no generalization of them is known to be preserved.
+pub fn strengthen_invariant(
+    loop_guard: &BExpr,
+    loop_body: &Commands,
+    current_invariant: &BExpr,
+    counterexample: &InterpreterMemory,
+) -> BExpr {
+    strengthen_invariant_within(
+        loop_guard,
+        loop_body,
+        current_invariant,
+        counterexample,
+        MAX_STRENGTHEN_ROUNDS,
+    )
+}
+
+fn strengthen_invariant_within(
+    loop_guard: &BExpr,
+    loop_body: &Commands,
+    current_invariant: &BExpr,
+    counterexample: &InterpreterMemory,
+    max_rounds: usize,
+) -> BExpr {
+    let conjoin = |constraints: Vec<BExpr>| {
+        constraints
+            .into_iter()
+            .fold(current_invariant.clone(), |acc, c| {
+                BExpr::logic(acc, LogicOp::And, c)
+            })
+    };
+    let concrete = conjoin(
+        counterexample
+            .variables
+            .iter()
+            .map(|(var, &value)| {
+                BExpr::rel(
+                    AExpr::Reference(Target::Variable(var.clone())),
+                    RelOp::Eq,
+                    AExpr::Number(value),
+                )
+            })
+            .collect(),
+    );
+
+    // NOTE: A single iteration of the loop, which aborts if the guard is false
+    let iteration = Commands(vec![Command::If(vec![Guard(
+        loop_guard.clone(),
+        loop_body.clone(),
+    )])]);
+    let assigned = iteration.fv().into_iter().all(|t| match t {
+        Target::Variable(var) => counterexample.get_var(&var).is_some(),
+        Target::Array(arr, ()) => counterexample.get_arr(&arr).is_some(),
+    });
+    if !assigned {
+        return concrete;
+    }
+
+    let pg = ProgramGraph::new(Determinism::NonDeterministic, &iteration);
+    let mut reached: HashSet<SignMemory> = [SignMemory {
+        variables: counterexample
+            .variables
+            .iter()
+            .map(|(var, &value)| (var.clone(), sign_of(value)))
+            .collect(),
+        arrays: counterexample
+            .arrays
+            .iter()
+            .map(|(arr, values)| (arr.clone(), values.iter().copied().map(sign_of).collect()))
+            .collect(),
+    }]
+    .into_iter()
+    .collect();
+    let mut frontier = reached.clone();
+
+    for _ in 0..max_rounds {
+        frontier = frontier
+            .into_iter()
+            .flat_map(|assignment| {
+                let facts = mono_analysis::<_, FiFo>(
+                    SignAnalysis {
+                        assignment,
+                        lengths: Default::default(),
+                    },
+                    &pg,
+                )
+                .facts;
+                facts.get(&Node::End).cloned().unwrap_or_default()
+            })
+            .filter(|mem| !reached.contains(mem))
+            .collect();
+        if frontier.is_empty() {
+            return conjoin(
+                counterexample
+                    .variables
+                    .keys()
+                    .filter_map(|var| {
+                        let signs: Signs = reached
+                            .iter()
+                            .filter_map(|mem| mem.get_var(var).copied())
+                            .collect();
+                        sign_constraint(var, signs)
+                    })
+                    .collect(),
+            );
+        }
+        reached.extend(frontier.iter().cloned());
+    }
+
+    concrete
+}
+
+/// A predicate which is true exactly when `var` has one of the given `signs`,
+/// or `None` if it imposes no constraint.
+fn sign_constraint(var: &Variable, signs: Signs) -> Option<BExpr> {
+    let op = if signs == Sign::Positive.into() {
+        RelOp::Gt
+    } else if signs == Sign::Zero.into() {
+        RelOp::Eq
+    } else if signs == Sign::Negative.into() {
+        RelOp::Lt
+    } else if signs == Signs::ZERO | Signs::POSITIVE {
+        RelOp::Ge
+    } else if signs == Signs::ZERO | Signs::NEGATIVE {
+        RelOp::Le
+    } else if signs == Signs::POSITIVE | Signs::NEGATIVE {
+        RelOp::Ne
+    } else {
+        return None;
+    };
+
+    Some(BExpr::rel(
+        AExpr::Reference(Target::Variable(var.clone())),
+        op,
+        AExpr::Number(0),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{guess_invariant, strengthen_invariant, strengthen_invariant_within};
+    use crate::{
+        ast::Command,
+        parse::{parse_bexpr, parse_commands},
+        sign::MemoryBuilder,
+    };
+
+    #[test]
+    fn strengthen_with_signs() {
+        let Command::Loop(guards) = &parse_commands("do x > 0 -> x := 0; y := y + 1 od")
+            .unwrap()
+            .0[0]
+        else {
+            unreachable!()
+        };
+        let counterexample = MemoryBuilder::new().var("x", 3).var("y", 2).build();
+
+        assert_eq!(
+            strengthen_invariant(
+                &guards[0].0,
+                &guards[0].1,
+                &parse_bexpr("true").unwrap(),
+                &counterexample,
+            ),
+            parse_bexpr("true && x >= 0 && y > 0").unwrap()
+        );
+
+        let partial = MemoryBuilder::new().var("x", 3).build();
+        assert_eq!(
+            strengthen_invariant(
+                &guards[0].0,
+                &guards[0].1,
+                &parse_bexpr("x >= 0").unwrap(),
+                &partial,
+            ),
+            parse_bexpr("x >= 0 && x = 3").unwrap()
+        );
+    }
+
+    #[test]
+    fn strengthen_within_bound() {
+        let Command::Loop(guards) = &parse_commands("do true -> x := x - 1 od").unwrap().0[0]
+        else {
+            unreachable!()
+        };
+        let counterexample = MemoryBuilder::new().var("x", 1).build();
+        let true_ = parse_bexpr("true").unwrap();
+
+        // NOTE: Reaching zero and then negative values takes two iterations,
+        // after which `x` may have any sign and is left unconstrained
+        assert_eq!(
+            strengthen_invariant_within(&guards[0].0, &guards[0].1, &true_, &counterexample, 3),
+            true_
+        );
+        assert_eq!(
+            strengthen_invariant_within(&guards[0].0, &guards[0].1, &true_, &counterexample, 1),
+            parse_bexpr("true && x = 1").unwrap()
+        );
+    }
+
+    #[test]
+    fn guessed_invariants() {
+        let Command::Loop(guards) = &parse_commands("do x < 10 -> x := x + 1 od").unwrap().0[0]
+        else {
+            unreachable!()
+        };
+        let pre = parse_bexpr("x >= 3").unwrap();
+
+        assert_eq!(
+            guess_invariant(&guards[0].0, &guards[0].1, &pre),
+            [
+                parse_bexpr("x > 0").unwrap(),
+                parse_bexpr("x >= 3").unwrap(),
+                parse_bexpr("x >= 3 && x + 1 >= 3").unwrap(),
+                parse_bexpr("x >= 3 && !(x < 10)").unwrap(),
+            ]
+        );
+
+        // NOTE: The sign analysis cannot evaluate functions, so there is no
+        // sign candidate
+        let Command::Loop(guards) = &parse_commands("do x < 10 -> x := min(x, 5) + 1 od")
+            .unwrap()
+            .0[0]
+        else {
+            unreachable!()
+        };
+        let pre = parse_bexpr("x > 0").unwrap();
+        assert_eq!(
+            guess_invariant(&guards[0].0, &guards[0].1, &pre),
+            [
+                parse_bexpr("x > 0").unwrap(),
+                parse_bexpr("x > 0 && min(x, 5) + 1 > 0").unwrap(),
+                parse_bexpr("x > 0 && !(x < 10)").unwrap(),
+            ]
+        );
+    }
+}
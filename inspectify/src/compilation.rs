@@ -202,8 +202,8 @@ async fn initialize_driver(dir: &Path, run: &RunOption) -> color_eyre::Result<Dr
 
         spinner.finish();
 
-        Ok(driver)
+        Ok(driver.with_limits(run.limits()))
     } else {
-        Ok(Driver::new(dir, &run.run))
+        Ok(Driver::new(dir, &run.run).with_limits(run.limits()))
     }
 }
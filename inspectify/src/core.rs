@@ -19,7 +19,7 @@ pub async fn generate_program(Json(analysis): Json<Analysis>) -> Json<String> {
 #[axum::debug_handler]
 pub async fn dot(Json((deterministic, src)): Json<(bool, String)>) -> Json<String> {
     let Ok(cmds) = checkr::parse::parse_commands(&src) else {
-        return "Parse error".to_string().into()
+        return "Parse error".to_string().into();
     };
     GraphEnv
         .run(
@@ -30,6 +30,7 @@ pub async fn dot(Json((deterministic, src)): Json<(bool, String)>) -> Json<Strin
                 } else {
                     Determinism::NonDeterministic
                 },
+                normalized: false,
             },
         )
         .expect("the input was just given, so it should work")
@@ -138,6 +138,7 @@ pub async fn graph(
                     true => Determinism::Deterministic,
                     false => Determinism::NonDeterministic,
                 },
+                normalized: false,
             },
         )
         .await
@@ -261,6 +262,26 @@ pub async fn analyze(
                 took: *took,
                 validation_result: None,
             },
+            checkr::driver::ExecError::UnsupportedAnalysis { output, time, .. } => {
+                AnalysisResponse {
+                    stdout: String::from_utf8(output.stdout.clone()).unwrap(),
+                    stderr: String::from_utf8(output.stderr.clone()).unwrap(),
+                    parsed_markdown: None,
+                    took: *time,
+                    validation_result: None,
+                }
+            }
+            checkr::driver::ExecError::CpuLimitExceeded { output, time }
+            | checkr::driver::ExecError::MemoryLimitExceeded { output, time }
+            | checkr::driver::ExecError::InvalidCapabilities { output, time, .. } => {
+                AnalysisResponse {
+                    stdout: String::from_utf8(output.stdout.clone()).unwrap(),
+                    stderr: String::from_utf8(output.stderr.clone()).unwrap(),
+                    parsed_markdown: None,
+                    took: *time,
+                    validation_result: None,
+                }
+            }
             checkr::driver::ExecError::Parse {
                 inner,
                 run_output,